@@ -1,21 +1,48 @@
 use edit_distance::edit_distance;
 use fnv::FnvHashMap;
+use std::collections::BTreeSet;
 
-/// Handles the line so we know if we found two or three times the same character
-/// Returns either (0,0) / (1,0) / (0,1) / (1,1)
-fn treat_line(input: &str) -> (usize, usize) {
+/// Computes the signature of an ID : the set of all the multiplicities
+/// of its letters. For example, "bababc" gives {1, 2, 3}
+pub fn signature(input: &str) -> BTreeSet<usize> {
     let mut map: FnvHashMap<char, usize> = FnvHashMap::default();
     input.chars().for_each(|a| *map.entry(a).or_insert(0) += 1);
+    map.values().cloned().collect()
+}
+
+/// Lists, for every given factor, the IDs whose signature contains said factor
+pub struct FactorReport<'a> {
+    /// The multiplicity looked for
+    pub factor: usize,
+    /// The IDs that have at least one letter appearing exactly `factor` times
+    pub ids: Vec<&'a str>,
+}
+
+/// Builds the report of which IDs contributed to each of the given factors
+pub fn checksum_report<'a>(input: &'a str, factors: &[usize]) -> Vec<FactorReport<'a>> {
+    let signatures: Vec<(&str, BTreeSet<usize>)> =
+        input.lines().map(|l| (l, signature(l))).collect();
+
+    factors
+        .iter()
+        .map(|&factor| FactorReport {
+            factor,
+            ids: signatures
+                .iter()
+                .filter(|(_, s)| s.contains(&factor))
+                .map(|(l, _)| *l)
+                .collect(),
+        })
+        .collect()
+}
 
-    let twos = match map.values().any(|&v| v == 2usize) {
-        true => 1,
-        _ => 0,
-    };
-    let threes = match map.values().any(|&v| v == 3usize) {
-        true => 1,
-        _ => 0,
-    };
-    (twos, threes)
+/// Computes the checksum of the input given any set of multiplicities :
+/// the product of the number of IDs contributing to each factor
+pub fn checksum(input: &str, factors: &[usize]) -> usize {
+    checksum_report(input, factors)
+        .iter()
+        .map(|r| r.ids.len())
+        .product()
 }
 
 /// Gets the common part of the two given &str into a String
@@ -29,11 +56,7 @@ fn get_common(a: &str, b: &str) -> String {
 /// Solves part one
 #[aoc(day2, part1)]
 fn part_one(input: &str) -> usize {
-    let numbers: (usize, usize) = input
-        .lines()
-        .map(|l| treat_line(l))
-        .fold((0, 0), |acc, i| (acc.0 + i.0, acc.1 + i.1));
-    numbers.0 * numbers.1
+    checksum(input, &[2, 3])
 }
 
 /// Solves part two
@@ -54,14 +77,27 @@ pub mod tests {
     use super::*;
 
     #[test]
-    fn day2_part1_treatline() {
-        assert_eq!(treat_line("abcdef"), (0, 0));
-        assert_eq!(treat_line("bababc"), (1, 1));
-        assert_eq!(treat_line("abbcde"), (1, 0));
-        assert_eq!(treat_line("abcccd"), (0, 1));
-        assert_eq!(treat_line("aabcdd"), (1, 0));
-        assert_eq!(treat_line("abcdee"), (1, 0));
-        assert_eq!(treat_line("ababab"), (0, 1));
+    fn day2_part1_signature() {
+        let sig = |s: &str| signature(s).into_iter().collect::<Vec<usize>>();
+        assert_eq!(sig("abcdef"), vec![1]);
+        assert_eq!(sig("bababc"), vec![1, 2, 3]);
+        assert_eq!(sig("abbcde"), vec![1, 2]);
+        assert_eq!(sig("abcccd"), vec![1, 3]);
+        assert_eq!(sig("aabcdd"), vec![1, 2]);
+        assert_eq!(sig("abcdee"), vec![1, 2]);
+        assert_eq!(sig("ababab"), vec![3]);
+        assert_eq!(sig("aaaabb"), vec![2, 4]);
+    }
+
+    #[test]
+    fn day2_part1_report() {
+        let input: &str = "abcdef\nbababc\naaaabb\nabcccd";
+        let report = checksum_report(input, &[2, 3, 4]);
+        assert_eq!(report[0].factor, 2);
+        assert_eq!(report[0].ids, vec!["bababc", "aaaabb"]);
+        assert_eq!(report[1].ids, vec!["bababc", "abcccd"]);
+        assert_eq!(report[2].ids, vec!["aaaabb"]);
+        assert_eq!(checksum(input, &[2, 3, 4]), 4);
     }
 
     #[test]
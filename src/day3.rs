@@ -1,8 +1,10 @@
-use fnv::FnvHashSet;
+use fnv::FnvHashMap;
+use std::collections::BTreeSet;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
 /// Struct that represents a Claim as described in the subject
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Claim {
     pub id: u32,
     pub coords: (u32, u32),
@@ -10,27 +12,63 @@ pub struct Claim {
 }
 
 impl Claim {
-    /// Checks if a claim contacts another
+    /// Exclusive end of the claim on both axes. A claim may end
+    /// beyond `u32::MAX`, so edges are computed as `u64`.
+    pub fn end(&self) -> (u64, u64) {
+        (
+            u64::from(self.coords.0) + u64::from(self.area.0),
+            u64::from(self.coords.1) + u64::from(self.area.1),
+        )
+    }
+
+    /// Checks if a claim contacts another.
+    /// A zero-area claim covers nothing, hence contacts nothing.
     pub fn contact(&self, other: &Self) -> bool {
-        // Basic formula to check collision between two box - adapted for the use case
-        !(self.coords.0 > other.coords.0 + other.area.0 - 1
-            || self.coords.0 + self.area.0 - 1 < other.coords.0
-            || self.coords.1 > other.coords.1 + other.area.1 - 1
-            || self.coords.1 + self.area.1 - 1 < other.coords.1)
+        // Basic formula to check collision between two box, using
+        // exclusive ends so that empty claims don't underflow
+        let (end, other_end) = (self.end(), other.end());
+        u64::from(self.coords.0) < other_end.0
+            && u64::from(other.coords.0) < end.0
+            && u64::from(self.coords.1) < other_end.1
+            && u64::from(other.coords.1) < end.1
+    }
+
+    /// Bounds of the rectangle shared by two claims, as
+    /// (x_min, x_max, y_min, y_max), empty if `min >= max` on an axis
+    fn common(&self, other: &Self) -> (u64, u64, u64, u64) {
+        let (end, other_end) = (self.end(), other.end());
+        (
+            u64::from(self.coords.0.max(other.coords.0)),
+            end.0.min(other_end.0),
+            u64::from(self.coords.1.max(other.coords.1)),
+            end.1.min(other_end.1),
+        )
     }
 
+    /// Iterates over the square inches shared by two claims.
+    /// Square inches beyond `u32::MAX` have no coordinates, and are left out.
     pub fn overlap(&self, other: &Self) -> impl Iterator<Item = (u32, u32)> {
-        // Calc the common rectangle
-        let x_min = self.coords.0.max(other.coords.0);
-        let x_max = (self.coords.0 + self.area.0).min(other.coords.0 + other.area.0);
-        let y_min = self.coords.1.max(other.coords.1);
-        let y_max = (self.coords.1 + self.area.1).min(other.coords.1 + other.area.1);
+        let last = u64::from(u32::MAX) + 1;
+        let (x_min, x_max, y_min, y_max) = self.common(other);
+        let (x_max, y_max) = (x_max.min(last), y_max.min(last));
 
         // Return an iterator
         (x_min..x_max)
-            .map(move |x| (y_min..y_max).map(move |y| (x, y)))
+            .map(move |x| (y_min..y_max).map(move |y| (x as u32, y as u32)))
             .flat_map(|i| i.into_iter())
     }
+
+    /// Computes the area (in square inches) shared by two claims
+    pub fn overlap_area(&self, other: &Self) -> u64 {
+        let (x_min, x_max, y_min, y_max) = self.common(other);
+        x_max.saturating_sub(x_min) * y_max.saturating_sub(y_min)
+    }
+
+    /// Checks if the claim covers the given square inch
+    pub fn covers(&self, x: u32, y: u32) -> bool {
+        let end = self.end();
+        x >= self.coords.0 && u64::from(x) < end.0 && y >= self.coords.1 && u64::from(y) < end.1
+    }
 }
 
 impl FromStr for Claim {
@@ -57,6 +95,441 @@ impl FromStr for Claim {
     }
 }
 
+/// Centered interval tree indexing the horizontal extent `[start, end)`
+/// of the Claims of a `Fabric`. Each node holds the Claims containing
+/// its center, the others being dispatched to the left or right subtree.
+struct IntervalTree {
+    center: u64,
+    /// Claims containing the center, as (start, index), sorted by increasing start
+    by_start: Vec<(u64, usize)>,
+    /// Claims containing the center, as (end, index), sorted by decreasing end
+    by_end: Vec<(u64, usize)>,
+    left: Option<Box<IntervalTree>>,
+    right: Option<Box<IntervalTree>>,
+}
+
+impl IntervalTree {
+    /// Builds the tree from a list of (start, end, index).
+    /// Empty intervals must have been filtered out beforehand.
+    fn build(mut intervals: Vec<(u64, u64, usize)>) -> Option<Box<IntervalTree>> {
+        if intervals.is_empty() {
+            return None;
+        }
+
+        // The start of the median interval is always contained in said
+        // interval, so every node holds at least one Claim
+        intervals.sort();
+        let center = intervals[intervals.len() / 2].0;
+
+        let mut left = vec![];
+        let mut right = vec![];
+        let mut by_start = vec![];
+        let mut by_end = vec![];
+        intervals.into_iter().for_each(|(start, end, idx)| {
+            if end <= center {
+                left.push((start, end, idx));
+            } else if start > center {
+                right.push((start, end, idx));
+            } else {
+                by_start.push((start, idx));
+                by_end.push((end, idx));
+            }
+        });
+        by_start.sort();
+        by_end.sort_by(|a, b| b.cmp(a));
+
+        Some(Box::new(IntervalTree {
+            center,
+            by_start,
+            by_end,
+            left: IntervalTree::build(left),
+            right: IntervalTree::build(right),
+        }))
+    }
+
+    /// Pushes in `found` the index of every interval overlapping `[lo, hi)`
+    fn query(&self, lo: u64, hi: u64, found: &mut Vec<usize>) {
+        if hi <= self.center {
+            // Only the Claims starting before `hi` can reach the query
+            self.by_start
+                .iter()
+                .take_while(|(start, _)| *start < hi)
+                .for_each(|(_, idx)| found.push(*idx));
+            if let Some(left) = &self.left {
+                left.query(lo, hi, found);
+            }
+        } else if lo > self.center {
+            // Only the Claims ending after `lo` can reach the query
+            self.by_end
+                .iter()
+                .take_while(|(end, _)| *end > lo)
+                .for_each(|(_, idx)| found.push(*idx));
+            if let Some(right) = &self.right {
+                right.query(lo, hi, found);
+            }
+        } else {
+            // The query contains the center, so does every Claim of this node
+            self.by_start.iter().for_each(|(_, idx)| found.push(*idx));
+            if let Some(left) = &self.left {
+                left.query(lo, hi, found);
+            }
+            if let Some(right) = &self.right {
+                right.query(lo, hi, found);
+            }
+        }
+    }
+}
+
+/// A piece of fabric holding a list of Claims, indexed so that
+/// spatial queries don't need to check every pair of Claims
+pub struct Fabric {
+    claims: Vec<Claim>,
+    /// Position of every Claim in `claims`, by ID
+    ids: FnvHashMap<u32, usize>,
+    index: Option<Box<IntervalTree>>,
+}
+
+impl Fabric {
+    /// Creates a new `Fabric` from the given Claims
+    pub fn new(claims: Vec<Claim>) -> Self {
+        let ids = claims.iter().enumerate().map(|(i, c)| (c.id, i)).collect();
+        let intervals = claims
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.area.0 > 0 && c.area.1 > 0)
+            .map(|(i, c)| (u64::from(c.coords.0), c.end().0, i))
+            .collect();
+        Fabric {
+            claims,
+            ids,
+            index: IntervalTree::build(intervals),
+        }
+    }
+
+    /// Gets all the Claims of the `Fabric`
+    pub fn claims(&self) -> &[Claim] {
+        &self.claims
+    }
+
+    /// Gets a Claim using its ID
+    pub fn get(&self, id: u32) -> Option<&Claim> {
+        self.ids.get(&id).map(|&i| &self.claims[i])
+    }
+
+    /// Finds the index of every Claim intersecting the rectangle
+    /// `[x_min, x_max) x [y_min, y_max)`
+    fn query(&self, x_min: u64, x_max: u64, y_min: u64, y_max: u64) -> Vec<usize> {
+        let mut found = vec![];
+        if x_min >= x_max || y_min >= y_max {
+            return found;
+        }
+        if let Some(index) = &self.index {
+            index.query(x_min, x_max, &mut found);
+        }
+        found.retain(|&i| {
+            let c = &self.claims[i];
+            u64::from(c.coords.1) < y_max && c.end().1 > y_min
+        });
+        found.sort();
+        found
+    }
+
+    /// Finds the index of every other Claim intersecting the Claim at index `idx`
+    fn intersecting_idx(&self, idx: usize) -> Vec<usize> {
+        let c = &self.claims[idx];
+        let end = c.end();
+        let mut found = self.query(u64::from(c.coords.0), end.0, u64::from(c.coords.1), end.1);
+        found.retain(|&i| i != idx);
+        found
+    }
+
+    /// Gets the IDs of the Claims covering the square inch (x, y)
+    pub fn claims_at(&self, x: u32, y: u32) -> Vec<u32> {
+        let (x, y) = (u64::from(x), u64::from(y));
+        self.query(x, x + 1, y, y + 1)
+            .into_iter()
+            .map(|i| self.claims[i].id)
+            .collect()
+    }
+
+    /// Computes the area shared by the two given Claims.
+    /// Returns None if one of the IDs is unknown
+    pub fn overlap_area(&self, a: u32, b: u32) -> Option<u64> {
+        Some(self.get(a)?.overlap_area(self.get(b)?))
+    }
+
+    /// Gets the IDs of the other Claims intersecting the given Claim
+    pub fn intersecting(&self, id: u32) -> Vec<u32> {
+        match self.ids.get(&id) {
+            Some(&idx) => self
+                .intersecting_idx(idx)
+                .into_iter()
+                .map(|i| self.claims[i].id)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Computes the connected components of the overlap graph, that is
+    /// groups of Claims linked by a chain of overlapping Claims.
+    /// Each component is sorted by ID, and components by their first ID.
+    pub fn components(&self) -> Vec<Vec<u32>> {
+        let mut visited = vec![false; self.claims.len()];
+        let mut components: Vec<Vec<u32>> = vec![];
+        for start in 0..self.claims.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut stack = vec![start];
+            let mut component = vec![];
+            while let Some(idx) = stack.pop() {
+                component.push(self.claims[idx].id);
+                self.intersecting_idx(idx).into_iter().for_each(|i| {
+                    if !visited[i] {
+                        visited[i] = true;
+                        stack.push(i);
+                    }
+                });
+            }
+            component.sort();
+            components.push(component);
+        }
+        components.sort();
+        components
+    }
+}
+
+/// A set of Claims that can be edited one Claim at a time, keeping
+/// the statistics of both parts up to date after each edit.
+/// Every edit costs time proportional to the area of the edited Claim.
+#[derive(Default)]
+pub struct LiveFabric {
+    claims: FnvHashMap<u32, Claim>,
+    /// IDs of the Claims covering each used square inch
+    cells: FnvHashMap<(u64, u64), Vec<u32>>,
+    /// Number of contested square inches inside each Claim
+    contested_cells: FnvHashMap<u32, usize>,
+    /// Number of square inches covered by two or more Claims
+    contested: usize,
+    /// IDs of the Claims that overlap nothing
+    uncontested: BTreeSet<u32>,
+}
+
+impl LiveFabric {
+    /// Creates an empty `LiveFabric`
+    pub fn new() -> Self {
+        LiveFabric::default()
+    }
+
+    /// Number of square inches covered by two or more Claims (part one)
+    pub fn contested(&self) -> usize {
+        self.contested
+    }
+
+    /// IDs of the Claims that overlap no other Claim (part two)
+    pub fn uncontested(&self) -> &BTreeSet<u32> {
+        &self.uncontested
+    }
+
+    /// Gets a Claim using its ID
+    pub fn get(&self, id: u32) -> Option<&Claim> {
+        self.claims.get(&id)
+    }
+
+    /// Adds a Claim. Returns false, leaving the `LiveFabric` untouched,
+    /// if a Claim with the same ID is already present
+    pub fn insert(&mut self, claim: Claim) -> bool {
+        if self.claims.contains_key(&claim.id) {
+            return false;
+        }
+
+        let mut own_contested = 0;
+        let end = claim.end();
+        for x in u64::from(claim.coords.0)..end.0 {
+            for y in u64::from(claim.coords.1)..end.1 {
+                let owners = self.cells.entry((x, y)).or_default();
+                owners.push(claim.id);
+                match owners.len() {
+                    1 => (),
+                    2 => {
+                        // The cell just became contested : the previous
+                        // owner loses its lone status
+                        self.contested += 1;
+                        own_contested += 1;
+                        let other = owners[0];
+                        *self.contested_cells.entry(other).or_insert(0) += 1;
+                        self.uncontested.remove(&other);
+                    }
+                    _ => own_contested += 1,
+                }
+            }
+        }
+
+        if own_contested == 0 {
+            self.uncontested.insert(claim.id);
+        }
+        self.contested_cells.insert(claim.id, own_contested);
+        self.claims.insert(claim.id, claim);
+        true
+    }
+
+    /// Removes a Claim using its ID, returning it if it was present
+    pub fn remove(&mut self, id: u32) -> Option<Claim> {
+        let claim = self.claims.remove(&id)?;
+
+        let end = claim.end();
+        for x in u64::from(claim.coords.0)..end.0 {
+            for y in u64::from(claim.coords.1)..end.1 {
+                let owners = self
+                    .cells
+                    .get_mut(&(x, y))
+                    .expect("Cell of a known Claim is not covered");
+                owners.retain(|&o| o != id);
+                match owners.len() {
+                    0 => {
+                        self.cells.remove(&(x, y));
+                    }
+                    1 => {
+                        // The cell is not contested anymore : the remaining
+                        // owner may become a lone Claim again
+                        self.contested -= 1;
+                        let other = owners[0];
+                        let count = self
+                            .contested_cells
+                            .get_mut(&other)
+                            .expect("Owner of a cell is not a known Claim");
+                        *count -= 1;
+                        if *count == 0 {
+                            self.uncontested.insert(other);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        self.contested_cells.remove(&id);
+        self.uncontested.remove(&id);
+        Some(claim)
+    }
+}
+
+/// Claims seen through coordinate compression : the fabric is cut
+/// along the edges of every Claim, so that each compressed cell is
+/// covered by the exact same Claims over its whole surface. This keeps
+/// the work independent of the actual coordinates, for huge fabrics.
+pub struct CompressedFabric {
+    /// Total area covered by two or more Claims
+    contested_area: u64,
+    /// IDs of the Claims that overlap nothing, sorted
+    uncontested: Vec<u32>,
+}
+
+impl CompressedFabric {
+    /// Computes the statistics of the given Claims
+    pub fn new(claims: &[Claim]) -> Self {
+        // Zero-area claims cover nothing : they don't produce any cell
+        let covering: Vec<&Claim> = claims
+            .iter()
+            .filter(|c| c.area.0 > 0 && c.area.1 > 0)
+            .collect();
+
+        let edges = |f: fn(&Claim) -> (u64, u64)| -> Vec<u64> {
+            let mut edges: Vec<u64> = covering
+                .iter()
+                .flat_map(|c| {
+                    let (start, end) = f(c);
+                    vec![start, end]
+                })
+                .collect();
+            edges.sort();
+            edges.dedup();
+            edges
+        };
+        let xs = edges(|c| (u64::from(c.coords.0), c.end().0));
+        let ys = edges(|c| (u64::from(c.coords.1), c.end().1));
+        let pos = |edges: &[u64], v: u64| edges.binary_search(&v).expect("Unknown edge");
+
+        // Compressed bounds of each Claim, as [x_min, x_max) x [y_min, y_max)
+        let bounds: Vec<(u32, usize, usize, usize, usize)> = covering
+            .iter()
+            .map(|c| {
+                let end = c.end();
+                (
+                    c.id,
+                    pos(&xs, u64::from(c.coords.0)),
+                    pos(&xs, end.0),
+                    pos(&ys, u64::from(c.coords.1)),
+                    pos(&ys, end.1),
+                )
+            })
+            .collect();
+
+        // Counts the Claims covering each compressed cell using a 2D
+        // difference array, then accumulating it
+        let (w, h) = (xs.len(), ys.len());
+        let mut coverage: Vec<i32> = vec![0; w * h];
+        bounds.iter().for_each(|&(_, x0, x1, y0, y1)| {
+            coverage[y0 * w + x0] += 1;
+            coverage[y0 * w + x1] -= 1;
+            coverage[y1 * w + x0] -= 1;
+            coverage[y1 * w + x1] += 1;
+        });
+        (0..h).for_each(|y| (1..w).for_each(|x| coverage[y * w + x] += coverage[y * w + x - 1]));
+        (1..h).for_each(|y| (0..w).for_each(|x| coverage[y * w + x] += coverage[(y - 1) * w + x]));
+
+        // Weights contested cells by their real size, and keeps 2D prefix
+        // sums of contested cells to check every Claim in constant time
+        let mut contested_area = 0u64;
+        // (the last edges start no cell, hence are skipped)
+        let mut contested_prefix: Vec<u32> = vec![0; (w + 1) * (h + 1)];
+        for y in 0..h.saturating_sub(1) {
+            for x in 0..w.saturating_sub(1) {
+                let contested = coverage[y * w + x] >= 2;
+                if contested {
+                    contested_area += (xs[x + 1] - xs[x]) * (ys[y + 1] - ys[y]);
+                }
+                contested_prefix[(y + 1) * (w + 1) + x + 1] = contested as u32
+                    + contested_prefix[y * (w + 1) + x + 1]
+                    + contested_prefix[(y + 1) * (w + 1) + x]
+                    - contested_prefix[y * (w + 1) + x];
+            }
+        }
+
+        let prefix = |x: usize, y: usize| contested_prefix[y * (w + 1) + x];
+        let mut uncontested: Vec<u32> = bounds
+            .iter()
+            .filter(|&&(_, x0, x1, y0, y1)| {
+                prefix(x1, y1) + prefix(x0, y0) == prefix(x0, y1) + prefix(x1, y0)
+            })
+            .map(|b| b.0)
+            .chain(
+                claims
+                    .iter()
+                    .filter(|c| c.area.0 == 0 || c.area.1 == 0)
+                    .map(|c| c.id),
+            )
+            .collect();
+        uncontested.sort();
+
+        CompressedFabric {
+            contested_area,
+            uncontested,
+        }
+    }
+
+    /// Total area covered by two or more Claims (part one)
+    pub fn contested_area(&self) -> u64 {
+        self.contested_area
+    }
+
+    /// IDs of the Claims that overlap no other Claim (part two)
+    pub fn uncontested(&self) -> &[u32] {
+        &self.uncontested
+    }
+}
+
 /// Generator that gives a list of Claims given the input
 #[aoc_generator(day3)]
 fn input_gen(input: &str) -> Vec<Claim> {
@@ -66,38 +539,45 @@ fn input_gen(input: &str) -> Vec<Claim> {
         .collect()
 }
 
+/// Largest fabric, in square inches, counted on a dense grid by `part_one`.
+/// Bigger fabrics go through a `CompressedFabric`.
+const DENSE_FABRIC_LIMIT: u64 = 1 << 24;
+
 /// Solves part one
 #[aoc(day3, part1)]
 fn part_one(input: &[Claim]) -> usize {
-    input
-        .into_iter()
-        // Flat_maps all the coordinates generated from the Claims
-        .flat_map(|c| {
-            input
-                .iter()
-                // If cc.id != c.id to prevent a Claim applying on itself
-                .filter_map(move |cc| {
-                    if cc.id != c.id {
-                        // Gets the overlapping coordinates between
-                        Some(c.overlap(cc))
-                    } else {
-                        None
-                    }
-                })
-                .flat_map(|i| i.into_iter())
-        })
-        // Collects as a HashSet to provide unicity
-        .collect::<FnvHashSet<(u32, u32)>>()
-        .len()
+    // Size of the fabric actually used by the Claims
+    let width = input.iter().map(|c| c.end().0).max().unwrap_or(0);
+    let height = input.iter().map(|c| c.end().1).max().unwrap_or(0);
+    if width * height > DENSE_FABRIC_LIMIT {
+        return CompressedFabric::new(input).contested_area() as usize;
+    }
+    let (width, height) = (width as usize, height as usize);
+
+    // Counts how many Claims cover every square inch of the fabric
+    let mut fabric: Vec<u32> = vec![0; width * height];
+    input.iter().for_each(|c| {
+        let (x, y) = (c.coords.0 as usize, c.coords.1 as usize);
+        let (w, h) = (c.area.0 as usize, c.area.1 as usize);
+        (y..y + h).for_each(|yy| {
+            fabric[yy * width + x..yy * width + x + w]
+                .iter_mut()
+                .for_each(|cell| *cell += 1)
+        });
+    });
+
+    fabric.iter().filter(|&&cell| cell >= 2).count()
 }
 
 /// Solves part two
 #[aoc(day3, part2)]
 fn part_two(input: &[Claim]) -> u32 {
     // Gets the lone Claim (the one that doesnt overlap with any other Claim)
-    let lone_claim = input
-        .into_iter()
-        .find(|c| !input.iter().any(|cc| cc.id != c.id && c.contact(cc)))
+    let fabric = Fabric::new(input.to_vec());
+    let lone_claim = fabric
+        .claims()
+        .iter()
+        .find(|c| fabric.intersecting(c.id).is_empty())
         .expect("Could not find lone claim");
 
     lone_claim.id
@@ -149,4 +629,89 @@ pub mod tests {
         assert_eq!(part_two(&claims), 3);
     }
 
+    /// Tests the coordinate-compressed statistics, with huge and empty Claims
+    #[test]
+    fn day3_compressed() {
+        let claims = input_gen("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+        let compressed = CompressedFabric::new(&claims);
+        assert_eq!(compressed.contested_area(), part_one(&claims) as u64);
+        assert_eq!(compressed.uncontested(), &[3]);
+
+        let claims = input_gen(
+            "#1 @ 1000000,3000000: 4000000x4000000
+#2 @ 3000000,1000000: 4000000x4000000
+#3 @ 5000000,5000000: 0x2000000
+#4 @ 9000000,9000000: 1x1
+#5 @ 3000000,3000000: 0x0",
+        );
+        let compressed = CompressedFabric::new(&claims);
+        assert_eq!(compressed.contested_area(), 4_000_000_000_000);
+        assert_eq!(compressed.uncontested(), &[3, 4, 5]);
+        // Too big for a dense grid
+        assert_eq!(part_one(&claims), 4_000_000_000_000);
+        assert!(!claims[2].contact(&claims[0]));
+        assert!(!claims[4].contact(&claims[4]));
+
+        // Claims ending beyond u32::MAX
+        let claims = input_gen("#1 @ 4000000000,0: 400000000x1\n#2 @ 4200000000,0: 300000000x2");
+        let compressed = CompressedFabric::new(&claims);
+        assert_eq!(compressed.contested_area(), 200_000_000);
+        assert_eq!(part_one(&claims), 200_000_000);
+        assert!(claims[0].contact(&claims[1]));
+        assert_eq!(claims[0].overlap_area(&claims[1]), 200_000_000);
+        assert!(claims[1].covers(u32::MAX, 1));
+        assert_eq!(Fabric::new(claims.clone()).intersecting(1), vec![2]);
+        let edge = input_gen("#3 @ 4294967295,0: 2x1\n#4 @ 4294967294,0: 3x1");
+        let mut live = LiveFabric::new();
+        edge.iter().for_each(|c| assert!(live.insert(c.clone())));
+        assert_eq!(live.contested(), 2);
+        assert_eq!(
+            edge[0].overlap(&edge[1]).collect::<Vec<_>>(),
+            vec![(u32::MAX, 0)]
+        );
+    }
+
+    /// Tests the statistics of the LiveFabric through edits
+    #[test]
+    fn day3_live_fabric() {
+        let claims = input_gen("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 2,2: 2x2");
+        let mut live = LiveFabric::new();
+        claims[..3]
+            .iter()
+            .for_each(|c| assert!(live.insert(c.clone())));
+        assert!(!live.insert(claims[0].clone()));
+        assert_eq!(live.contested(), part_one(&claims[..3]));
+        assert_eq!(live.uncontested().iter().collect::<Vec<_>>(), vec![&3]);
+
+        assert!(live.insert(claims[3].clone()));
+        assert_eq!(live.contested(), part_one(&claims));
+        assert_eq!(live.uncontested().iter().collect::<Vec<_>>(), vec![&3]);
+
+        assert_eq!(live.remove(2).map(|c| c.id), Some(2));
+        assert_eq!(live.remove(2), None);
+        assert_eq!(live.contested(), 2);
+        assert_eq!(live.uncontested().iter().collect::<Vec<_>>(), vec![&3]);
+
+        live.remove(4);
+        assert_eq!(live.contested(), 0);
+        assert_eq!(live.uncontested().iter().collect::<Vec<_>>(), vec![&1, &3]);
+    }
+
+    /// Tests the spatial queries of the Fabric
+    #[test]
+    fn day3_fabric() {
+        let fabric = Fabric::new(input_gen(
+            "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 6,6: 3x3\n#5 @ 20,20: 1x1",
+        ));
+        assert_eq!(fabric.claims_at(3, 3), vec![1, 2]);
+        assert_eq!(fabric.claims_at(6, 6), vec![3, 4]);
+        assert_eq!(fabric.claims_at(0, 0), Vec::<u32>::new());
+        assert_eq!(fabric.overlap_area(1, 2), Some(4));
+        assert_eq!(fabric.overlap_area(1, 3), Some(0));
+        assert_eq!(fabric.overlap_area(1, 42), None);
+        assert_eq!(fabric.intersecting(1), vec![2]);
+        assert_eq!(fabric.intersecting(3), vec![4]);
+        assert_eq!(fabric.intersecting(5), Vec::<u32>::new());
+        assert_eq!(fabric.components(), vec![vec![1, 2], vec![3, 4], vec![5]]);
+    }
 }
@@ -1,17 +1,119 @@
+use chrono::NaiveDate;
 use chrono::NaiveDateTime;
+use chrono::NaiveTime;
 use chrono::Timelike;
 use fnv::FnvHashMap;
+use serde_json::json;
 use std::cmp::Ordering;
 use std::error::Error;
+use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
 
+/// Gets the date of the shift happening at the given timestamp.
+/// Shifts are about the midnight hour, so anything happening in
+/// the afternoon or the evening belongs to the next day's shift.
+pub fn shift_date(timestamp: NaiveDateTime) -> NaiveDate {
+    if timestamp.hour() >= 12 {
+        timestamp.date().succ_opt().expect("Date out of range")
+    } else {
+        timestamp.date()
+    }
+}
+
+/// Projects the sleep interval `[start, end)` onto the midnight hour
+/// of the given date, which is the only hour the subject cares about.
+/// Returns the range of minutes (between 0 and 60) spent asleep.
+fn midnight_minutes(date: NaiveDate, start: NaiveDateTime, end: NaiveDateTime) -> Range<u32> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    let minute = |ts: NaiveDateTime| (ts - midnight).num_minutes().clamp(0, 60) as u32;
+    let (from, to) = (minute(start), minute(end));
+    from..to.max(from)
+}
+
+/// A single night of duty of a `Guard`, with its naps and the minutes
+/// of the midnight hour during which said `Guard` was asleep
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shift {
+    /// Date of the `Shift` (see `shift_date`)
+    pub date: NaiveDate,
+    /// ID of the `Guard` on duty
+    pub guard: u32,
+    /// Real time intervals `[start, end)` spent asleep
+    pub naps: Vec<(NaiveDateTime, NaiveDateTime)>,
+    /// Minutes of the midnight hour spent asleep
+    pub asleep: [bool; 60],
+}
+
+impl Shift {
+    /// Creates a new `Shift`, during which the `Guard` did not sleep yet
+    pub fn new(date: NaiveDate, guard: u32) -> Self {
+        Shift {
+            date,
+            guard,
+            naps: vec![],
+            asleep: [false; 60],
+        }
+    }
+
+    /// Records a nap between the two given timestamps
+    pub fn add_sleep(&mut self, start: NaiveDateTime, end: NaiveDateTime) {
+        self.naps.push((start, end));
+        midnight_minutes(self.date, start, end).for_each(|m| self.asleep[m as usize] = true);
+    }
+}
+
+/// Renders the `Shift`s as the table shown in the subject :
+///
+/// ```text
+/// Date   ID   Minute
+///             000000000011111111112222222222333333333344444444445555555555
+///             012345678901234567890123456789012345678901234567890123456789
+/// 11-01  #10  .....####################.....#########################.....
+/// ```
+pub fn render_shifts(shifts: &[Shift]) -> String {
+    // The ID column is wide enough for the biggest ID
+    let id_width = shifts
+        .iter()
+        .map(|s| s.guard.to_string().len() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(3)
+        + 2;
+    let margin = " ".repeat(7 + id_width);
+
+    let mut table = format!("Date   {:<width$}Minute\n", "ID", width = id_width);
+    table.push_str(&margin);
+    (0..60).for_each(|m| table.push_str(&(m / 10).to_string()));
+    table.push('\n');
+    table.push_str(&margin);
+    (0..60).for_each(|m| table.push_str(&(m % 10).to_string()));
+    shifts.iter().for_each(|s| {
+        let mask: String = s
+            .asleep
+            .iter()
+            .map(|&a| if a { '#' } else { '.' })
+            .collect();
+        table.push_str(&format!(
+            "\n{}  {:<width$}{}",
+            s.date.format("%m-%d"),
+            format!("#{}", s.guard),
+            mask,
+            width = id_width
+        ));
+    });
+    table
+}
+
 /// A struct representing a Guard, with its ID
 /// and its sleep record
 pub struct Guard {
     /// Unique ID of the `Guard`
-    id: u32,
+    pub id: u32,
     /// Sleep records of the `Guard`
     pub asleep: FnvHashMap<u32, usize>,
+    /// Every `Shift` of the `Guard`, in chronological order
+    pub shifts: Vec<Shift>,
 }
 
 impl Guard {
@@ -20,15 +122,15 @@ impl Guard {
         Guard {
             id,
             asleep: FnvHashMap::default(),
+            shifts: vec![],
         }
     }
 
-    /// Adds a sleep record from the two given timestamps
+    /// Adds a sleep record from the two given timestamps.
+    /// Only the minutes of the midnight hour of the shift count.
     pub fn add_sleep_schedule(&mut self, start: NaiveDateTime, end: NaiveDateTime) {
-        let start_min = start.hour() * 60 + start.minute();
-        let end_min = end.hour() * 60 + end.minute();
-        (start_min..end_min).for_each(|m| {
-            let min = self.asleep.entry(m % 60).or_insert(0usize);
+        midnight_minutes(shift_date(start), start, end).for_each(|m| {
+            let min = self.asleep.entry(m).or_insert(0usize);
             *min += 1;
         });
     }
@@ -51,16 +153,89 @@ impl Guard {
     /// sleep record. Returns the minute and the number of times the
     /// `Guard` did sleep at said minute. Apparently, a `Guard`'s sleeping
     /// records can be empty so we need to handle the case of `asleep` being
-    /// empty ... I chose to return (0,0).
+    /// empty ... I chose to return (0,0). Ties go to the earliest minute.
     pub fn max_sleeping_minute(&self) -> (u32, u32) {
-        let (k, v) = self.asleep.iter().max_by_key(|a| a.1).unwrap_or((&0, &0));
+        let (k, v) = self
+            .asleep
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .unwrap_or((&0, &0));
         (*k, *v as u32)
     }
+
+    /// Number of nights the `Guard` was on duty
+    pub fn nights(&self) -> usize {
+        self.shifts.len()
+    }
+
+    /// Iterates over the duration, in minutes, of every nap of the `Guard`
+    fn naps(&self) -> impl Iterator<Item = i64> + '_ {
+        self.shifts
+            .iter()
+            .flat_map(|s| s.naps.iter())
+            .map(|(start, end)| (*end - *start).num_minutes())
+    }
+
+    /// Duration, in minutes, of the longest single nap of the `Guard`
+    pub fn longest_nap(&self) -> i64 {
+        self.naps().max().unwrap_or(0)
+    }
+
+    /// Average time, in minutes, spent asleep per shift.
+    /// Returns 0 if the `Guard` never was on duty.
+    pub fn average_sleep(&self) -> f64 {
+        match self.nights() {
+            0 => 0.,
+            n => self.naps().sum::<i64>() as f64 / n as f64,
+        }
+    }
+
+    /// Probability of the `Guard` being asleep at each minute
+    /// of the midnight hour, on any given shift
+    pub fn sleep_probability(&self) -> [f64; 60] {
+        let mut probability = [0.; 60];
+        if self.nights() > 0 {
+            self.asleep.iter().for_each(|(&m, &count)| {
+                probability[m as usize] = count as f64 / self.nights() as f64
+            });
+        }
+        probability
+    }
+}
+
+/// Finds the IDs of the `Guard`s asleep at the given timestamp
+pub fn asleep_at(guards: &[Guard], timestamp: NaiveDateTime) -> Vec<u32> {
+    guards
+        .iter()
+        .filter(|g| {
+            g.shifts
+                .iter()
+                .flat_map(|s| s.naps.iter())
+                .any(|(start, end)| *start <= timestamp && timestamp < *end)
+        })
+        .map(|g| g.id)
+        .collect()
+}
+
+/// Finds out the minute of the midnight hour during which the most
+/// `Guard`s were asleep across the whole log. Returns said minute (the
+/// earliest one in case of a tie) and the number of times a `Guard` was
+/// asleep at that minute, or (0,0) if nobody ever slept.
+pub fn busiest_minute(guards: &[Guard]) -> (u32, usize) {
+    let mut minutes = [0usize; 60];
+    guards
+        .iter()
+        .flat_map(|g| g.asleep.iter())
+        .for_each(|(&m, &count)| minutes[m as usize] += count);
+    (0..60u32)
+        .map(|m| (m, minutes[m as usize]))
+        .fold((0, 0), |acc, i| if i.1 > acc.1 { i } else { acc })
 }
 
 /// A `GuardBuilder` is a struct implementing a
 /// builder pattern for the `Guard` structs, that will
 /// create a list of `Guard`s based on given `Instruction`s
+#[derive(Default)]
 pub struct GuardBuilder {
     pub done: Vec<Guard>,
     building: Option<Guard>,
@@ -81,25 +256,30 @@ impl GuardBuilder {
     /// Adds an instruction to the builder
     pub fn with_instr(self, instr: Instruction) -> Self {
         match instr.action {
-            Action::Start(id) => self.handle_start(id),
+            Action::Start(id) => self.handle_start(id, instr.timestamp),
             Action::Sleep => self.handle_sleep(instr.timestamp),
             Action::Wake => self.handle_wake(instr.timestamp),
         }
     }
 
     /// Handles the receiving of an `Action::Start`
-    fn handle_start(mut self, id: u32) -> Self {
+    fn handle_start(mut self, id: u32, timestamp: NaiveDateTime) -> Self {
         if let Some(g) = self.building {
             // If we already have an existing `Guard` in the list with
             // the ID of the one being built previously, we must not
             // forget to merge the sleep records
             match self.done.iter_mut().find(|gg| g.id == gg.id) {
-                Some(existing) => existing.append_asleep(g.asleep),
+                Some(existing) => {
+                    existing.append_asleep(g.asleep);
+                    existing.shifts.extend(g.shifts);
+                }
                 None => self.done.push(g),
             }
         }
         self.start_ts = None;
-        self.building = Some(Guard::new(id));
+        let mut guard = Guard::new(id);
+        guard.shifts.push(Shift::new(shift_date(timestamp), id));
+        self.building = Some(guard);
         self
     }
 
@@ -112,11 +292,13 @@ impl GuardBuilder {
     /// Handles the receiving of an `Action::Wake`
     fn handle_wake(mut self, timestamp: NaiveDateTime) -> Self {
         // When receiving a Wake signal, we must add the sleep record
-        // to the `Guard`'s history
-        if let Some(ts) = self.start_ts {
-            let mut guard = self.building.unwrap();
+        // to the `Guard`'s history. A wake without a `Guard` on duty or
+        // without a prior sleep is ignored (see `validate`)
+        if let (Some(ts), Some(guard)) = (self.start_ts.take(), self.building.as_mut()) {
             guard.add_sleep_schedule(ts, timestamp);
-            self.building = Some(guard);
+            if let Some(shift) = guard.shifts.last_mut() {
+                shift.add_sleep(ts, timestamp);
+            }
         }
         self
     }
@@ -125,7 +307,10 @@ impl GuardBuilder {
     pub fn build(mut self) -> Vec<Guard> {
         if let Some(g) = self.building {
             match self.done.iter_mut().find(|gg| g.id == gg.id) {
-                Some(existing) => existing.append_asleep(g.asleep),
+                Some(existing) => {
+                    existing.append_asleep(g.asleep);
+                    existing.shifts.extend(g.shifts);
+                }
                 None => self.done.push(g),
             }
         }
@@ -171,7 +356,7 @@ impl FromStr for Instruction {
     fn from_str(input: &str) -> Result<Self, Box<dyn Error>> {
         let parts: Vec<&str> = input.split("] ").collect();
         let timestamp: NaiveDateTime = NaiveDateTime::parse_from_str(parts[0], "[%Y-%m-%d %H:%M")?;
-        let action: Action = parts[1].trim().parse()?;
+        let action: Action = parts.get(1).ok_or("Missing action")?.trim().parse()?;
 
         Ok(Instruction { timestamp, action })
     }
@@ -187,14 +372,99 @@ impl FromStr for Action {
             s if s.to_string().starts_with("falls") => Ok(Action::Sleep),
             s if s.to_string().starts_with("Guard") => {
                 let parts: Vec<&str> = s.split(" ").collect();
-                let guard_id: u32 = parts[1].replace("#", "").trim().parse::<u32>()?;
+                let guard_id: u32 = parts
+                    .get(1)
+                    .ok_or("Missing guard ID")?
+                    .replace("#", "")
+                    .trim()
+                    .parse::<u32>()?;
                 Ok(Action::Start(guard_id))
             }
-            _ => unreachable!(),
+            s => Err(format!("Unknown action \"{}\"", s).into()),
         }
     }
 }
 
+/// Sorts the `Guard`s by ID, so that exports are stable
+fn sorted_by_id(guards: &[Guard]) -> Vec<&Guard> {
+    let mut sorted: Vec<&Guard> = guards.iter().collect();
+    sorted.sort_by_key(|g| g.id);
+    sorted
+}
+
+/// Number of times a `Guard` was asleep at each minute of the midnight hour
+fn minute_counts(guard: &Guard) -> Vec<usize> {
+    (0..60)
+        .map(|m| guard.asleep.get(&m).cloned().unwrap_or(0))
+        .collect()
+}
+
+/// Lists every `Shift`, ordered by `Guard` ID then by date
+fn sorted_shifts(guards: &[Guard]) -> Vec<&Shift> {
+    sorted_by_id(guards)
+        .into_iter()
+        .flat_map(|g| {
+            let mut shifts: Vec<&Shift> = g.shifts.iter().collect();
+            shifts.sort_by_key(|s| s.date);
+            shifts
+        })
+        .collect()
+}
+
+/// Exports the guard x minute sleep matrix as CSV : one row per `Guard`,
+/// holding the number of times it was asleep at each minute
+pub fn sleep_matrix_csv(guards: &[Guard]) -> String {
+    let mut csv = String::from("guard");
+    (0..60).for_each(|m| csv.push_str(&format!(",{}", m)));
+    sorted_by_id(guards).into_iter().for_each(|g| {
+        csv.push_str(&format!("\n{}", g.id));
+        minute_counts(g)
+            .iter()
+            .for_each(|c| csv.push_str(&format!(",{}", c)));
+    });
+    csv
+}
+
+/// Exports the guard x minute sleep matrix as JSON :
+/// `[{"guard": 10, "minutes": [0, 0, ...]}, ...]`
+pub fn sleep_matrix_json(guards: &[Guard]) -> String {
+    let rows: Vec<serde_json::Value> = sorted_by_id(guards)
+        .into_iter()
+        .map(|g| json!({ "guard": g.id, "minutes": minute_counts(g) }))
+        .collect();
+    serde_json::Value::from(rows).to_string()
+}
+
+/// Exports the asleep mask of every `Shift` as CSV : one row per `Shift`,
+/// holding 1 for each minute spent asleep and 0 otherwise
+pub fn shifts_csv(guards: &[Guard]) -> String {
+    let mut csv = String::from("date,guard");
+    (0..60).for_each(|m| csv.push_str(&format!(",{}", m)));
+    sorted_shifts(guards).into_iter().for_each(|s| {
+        csv.push_str(&format!("\n{},{}", s.date, s.guard));
+        s.asleep
+            .iter()
+            .for_each(|&a| csv.push_str(if a { ",1" } else { ",0" }));
+    });
+    csv
+}
+
+/// Exports the asleep mask of every `Shift` as JSON :
+/// `[{"date": "1518-11-01", "guard": 10, "asleep": [false, ...]}, ...]`
+pub fn shifts_json(guards: &[Guard]) -> String {
+    let rows: Vec<serde_json::Value> = sorted_shifts(guards)
+        .into_iter()
+        .map(|s| {
+            json!({
+                "date": s.date.to_string(),
+                "guard": s.guard,
+                "asleep": s.asleep.to_vec(),
+            })
+        })
+        .collect();
+    serde_json::Value::from(rows).to_string()
+}
+
 /// Generates the guards from the input
 #[aoc_generator(day4)]
 fn gen_guards(input: &str) -> Vec<Guard> {
@@ -215,32 +485,258 @@ fn gen_guards(input: &str) -> Vec<Guard> {
         .build()
 }
 
+/// The various inconsistencies that can be found in a guard log
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum AnomalyKind {
+    /// The line could not be parsed, with the reason why
+    Unparsable(String),
+    /// A `Guard` wakes up without having fallen asleep
+    OrphanWake,
+    /// A `Guard` falls asleep and never wakes up before the next
+    /// `Action::Sleep`, the next shift or the end of the log
+    UnclosedSleep,
+    /// The event happens at the same time as the previous one
+    DuplicateTimestamp,
+    /// The event happens before any `Guard` began its shift
+    EventBeforeShift,
+}
+
+/// An anomaly found in a guard log, located by its line (starting at 1)
+/// and its timestamp, if the latter could be parsed
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Anomaly {
+    pub line: usize,
+    pub timestamp: Option<NaiveDateTime>,
+    pub kind: AnomalyKind,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(ts) = self.timestamp {
+            write!(f, " [{}]", ts.format("%Y-%m-%d %H:%M"))?;
+        }
+        match &self.kind {
+            AnomalyKind::Unparsable(reason) => write!(f, ": unparsable ({})", reason),
+            AnomalyKind::OrphanWake => write!(f, ": wakes up without falling asleep"),
+            AnomalyKind::UnclosedSleep => write!(f, ": falls asleep and never wakes up"),
+            AnomalyKind::DuplicateTimestamp => write!(f, ": duplicate timestamp"),
+            AnomalyKind::EventBeforeShift => write!(f, ": event before any shift"),
+        }
+    }
+}
+
+/// Checks a guard log, reporting every anomaly that the lenient
+/// `GuardBuilder` would otherwise silently ignore
+pub fn validate(input: &str) -> Vec<Anomaly> {
+    let mut anomalies: Vec<Anomaly> = vec![];
+
+    // Parses the instructions, keeping their line number
+    let mut instructions: Vec<(usize, Instruction)> = vec![];
+    input
+        .lines()
+        .enumerate()
+        .for_each(|(i, l)| match l.parse() {
+            Ok(instr) => instructions.push((i + 1, instr)),
+            Err(e) => anomalies.push(Anomaly {
+                line: i + 1,
+                timestamp: None,
+                kind: AnomalyKind::Unparsable(e.to_string()),
+            }),
+        });
+
+    // Stable sort, so that duplicated timestamps keep the order of the log
+    instructions.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut on_shift = false;
+    let mut asleep: Option<(usize, NaiveDateTime)> = None;
+    let mut previous: Option<NaiveDateTime> = None;
+    for (line, instr) in instructions {
+        let anomaly = |kind| Anomaly {
+            line,
+            timestamp: Some(instr.timestamp),
+            kind,
+        };
+        if previous == Some(instr.timestamp) {
+            anomalies.push(anomaly(AnomalyKind::DuplicateTimestamp));
+        }
+        previous = Some(instr.timestamp);
+
+        match instr.action {
+            Action::Start(_) => on_shift = true,
+            _ if !on_shift => {
+                anomalies.push(anomaly(AnomalyKind::EventBeforeShift));
+                continue;
+            }
+            Action::Wake if asleep.is_none() => anomalies.push(anomaly(AnomalyKind::OrphanWake)),
+            _ => (),
+        }
+
+        // Any action but a wake leaves the current sleep unclosed
+        if instr.action != Action::Wake {
+            if let Some((line, ts)) = asleep {
+                anomalies.push(Anomaly {
+                    line,
+                    timestamp: Some(ts),
+                    kind: AnomalyKind::UnclosedSleep,
+                });
+            }
+        }
+        asleep = match instr.action {
+            Action::Sleep => Some((line, instr.timestamp)),
+            _ => None,
+        };
+    }
+
+    if let Some((line, ts)) = asleep {
+        anomalies.push(Anomaly {
+            line,
+            timestamp: Some(ts),
+            kind: AnomalyKind::UnclosedSleep,
+        });
+    }
+    anomalies
+}
+
+/// Strict version of the generator : rejects the log if it contains
+/// any anomaly, returning all of them
+pub fn gen_guards_strict(input: &str) -> Result<Vec<Guard>, Vec<Anomaly>> {
+    let anomalies = validate(input);
+    if anomalies.is_empty() {
+        Ok(gen_guards(input))
+    } else {
+        Err(anomalies)
+    }
+}
+
+/// A strategy to choose the `Guard` to sneak past, and the minute
+/// at which to do so
+pub trait Strategy {
+    /// Name used to look the `Strategy` up with `strategy`.
+    /// Built-in strategies also label their runner with it.
+    fn name(&self) -> &'static str;
+
+    /// Chooses a `Guard` and a minute, or None if there is no `Guard`
+    fn choose<'a>(&self, guards: &'a [Guard]) -> Option<(&'a Guard, u32)>;
+}
+
+/// Finds the `Guard` with the highest given key.
+/// Ties are broken in favor of the lowest ID, to stay deterministic.
+fn max_guard_by<K: PartialOrd>(guards: &[Guard], key: impl Fn(&Guard) -> K) -> Option<&Guard> {
+    guards.iter().max_by(|a, b| {
+        key(a)
+            .partial_cmp(&key(b))
+            .unwrap_or(Ordering::Equal)
+            .then(b.id.cmp(&a.id))
+    })
+}
+
+/// Strategy 1 of the subject : the `Guard` that slept the most,
+/// at the minute said `Guard` slept the most
+pub struct MostAsleep;
+
+impl Strategy for MostAsleep {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn choose<'a>(&self, guards: &'a [Guard]) -> Option<(&'a Guard, u32)> {
+        let guard = max_guard_by(guards, |g| g.total_sleeping())?;
+        Some((guard, guard.max_sleeping_minute().0))
+    }
+}
+
+/// Strategy 2 of the subject : the `Guard` most frequently asleep
+/// on the same minute, at said minute
+pub struct MostFrequentMinute;
+
+impl Strategy for MostFrequentMinute {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn choose<'a>(&self, guards: &'a [Guard]) -> Option<(&'a Guard, u32)> {
+        let guard = max_guard_by(guards, |g| g.max_sleeping_minute().1)?;
+        Some((guard, guard.max_sleeping_minute().0))
+    }
+}
+
+/// The `Guard` with the highest probability of being asleep at
+/// a given minute of any shift, at said minute
+pub struct MostConsistent;
+
+impl Strategy for MostConsistent {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn choose<'a>(&self, guards: &'a [Guard]) -> Option<(&'a Guard, u32)> {
+        let guard = max_guard_by(guards, |g| {
+            g.sleep_probability()[g.max_sleeping_minute().0 as usize]
+        })?;
+        Some((guard, guard.max_sleeping_minute().0))
+    }
+}
+
+/// Gets a built-in `Strategy` using its name
+pub fn strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    strategies().into_iter().find(|s| s.name() == name)
+}
+
+/// Applies the given `Strategy`, returning the ID of the
+/// chosen `Guard` multiplied by the chosen minute
+pub fn solve(input: &[Guard], strategy: &dyn Strategy) -> u32 {
+    let (guard, minute) = strategy
+        .choose(input)
+        .expect("Couldn't find max sleeping guard");
+    guard.id * minute
+}
+
 /// Solves the part one
 #[aoc(day4, part1)]
 fn part_one(input: &[Guard]) -> u32 {
-    // Gets the `Guard` that slept the most
-    let max_sleeping_guard = input
-        .into_iter()
-        .max_by_key(|g| g.total_sleeping())
-        .expect("Couldn't find max sleeping guard");
-
-    // Computes the minute where said `Guard` slept the most
-    let max_sleeping_minute = max_sleeping_guard.max_sleeping_minute();
-    max_sleeping_guard.id * max_sleeping_minute.0
+    solve(input, &MostAsleep)
 }
 
 /// Solves the part two
 #[aoc(day4, part2)]
 fn part_two(input: &[Guard]) -> u32 {
-    // Gets the `Guard` that slept the most on a given minute
-    let max_sleeping_guard = input
-        .iter()
-        .max_by_key(|g| g.max_sleeping_minute().1)
-        .expect("Couldn't find max sleeping guard");
+    solve(input, &MostFrequentMinute)
+}
+
+/// Declares the built-in strategies, from a single list of
+/// `part name: Type` : gives each `Type` its name, lists it in
+/// `strategies`, and registers a runner for the given part, labelled
+/// with the name.
+///
+/// cargo-aoc runs every runner of a day and prints its label : no
+/// runner can be chosen from the command line. Looking a `Strategy` up
+/// by name is done with `strategy`, and a custom `Strategy` is run by
+/// giving it to `solve`.
+macro_rules! builtin_strategies {
+    ($($part:ident $name:ident: $ty:ident),* $(,)?) => {
+        $(
+            impl $ty {
+                const NAME: &'static str = stringify!($name);
+            }
+
+            #[aoc(day4, $part, $name)]
+            fn $name(input: &[Guard]) -> u32 {
+                solve(input, &$ty)
+            }
+        )*
 
-    // Computes the minute where said `Guard` slept the most
-    let max_sleeping_minute = max_sleeping_guard.max_sleeping_minute();
-    max_sleeping_guard.id * max_sleeping_minute.0
+        /// Lists every built-in `Strategy`
+        pub fn strategies() -> Vec<Box<dyn Strategy>> {
+            vec![$(Box::new($ty)),*]
+        }
+    };
+}
+
+builtin_strategies! {
+    part1 most_asleep: MostAsleep,
+    part2 most_frequent_minute: MostFrequentMinute,
+    part2 most_consistent: MostConsistent,
 }
 
 #[cfg(test)]
@@ -309,6 +805,197 @@ pub mod tests {
         assert_eq!(guard_99.max_sleeping_minute().1, 3);
     }
 
+    #[test]
+    fn test_parse_unknown_action() {
+        assert!("sings a song".parse::<Action>().is_err());
+        assert!("[1518-11-01 00:00]".parse::<Instruction>().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(TEST_INPUT), vec![]);
+        assert!(gen_guards_strict(TEST_INPUT).is_ok());
+
+        let input = "[1518-10-31 23:50] wakes up
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-02 00:40] wakes up
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:45] sings a song
+[1518-11-01 00:00] Guard";
+        let anomalies = validate(input);
+        let kinds: Vec<(usize, AnomalyKind)> =
+            anomalies.iter().map(|a| (a.line, a.kind.clone())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    7,
+                    AnomalyKind::Unparsable("Unknown action \"sings a song\"".into())
+                ),
+                (8, AnomalyKind::Unparsable("Missing guard ID".into())),
+                (1, AnomalyKind::EventBeforeShift),
+                (3, AnomalyKind::UnclosedSleep),
+                (5, AnomalyKind::OrphanWake),
+                (6, AnomalyKind::DuplicateTimestamp),
+                (6, AnomalyKind::UnclosedSleep),
+            ]
+        );
+        assert_eq!(
+            anomalies[4].to_string(),
+            "line 5 [1518-11-02 00:40]: wakes up without falling asleep"
+        );
+        assert!(gen_guards_strict(input).is_err());
+
+        // The lenient builder doesn't panic on a log starting with a wake
+        let guards = gen_guards("[1518-11-01 00:25] wakes up\n[1518-11-01 00:30] falls asleep");
+        assert!(guards.is_empty());
+    }
+
+    #[test]
+    fn test_render_shifts() {
+        let guards = gen_guards(TEST_INPUT);
+        let mut shifts: Vec<Shift> = guards
+            .iter()
+            .flat_map(|g| g.shifts.iter().cloned())
+            .collect();
+        shifts.sort_by_key(|s| s.date);
+        assert_eq!(
+            render_shifts(&shifts),
+            "Date   ID   Minute
+            000000000011111111112222222222333333333344444444445555555555
+            012345678901234567890123456789012345678901234567890123456789
+11-01  #10  .....####################.....#########################.....
+11-02  #99  ........................................##########..........
+11-03  #10  ........................#####...............................
+11-04  #99  ....................................##########..............
+11-05  #99  .............................................##########....."
+        );
+    }
+
+    #[test]
+    fn test_sleep_across_hours() {
+        let guards = gen_guards(
+            "[1518-11-01 23:40] Guard #10 begins shift
+[1518-11-01 23:50] falls asleep
+[1518-11-02 00:10] wakes up
+[1518-11-02 23:58] Guard #10 begins shift
+[1518-11-03 00:30] falls asleep
+[1518-11-03 02:10] wakes up",
+        );
+        let guard = &guards[0];
+        let dates: Vec<String> = guard
+            .shifts
+            .iter()
+            .map(|s| s.date.format("%m-%d").to_string())
+            .collect();
+        assert_eq!(dates, vec!["11-02", "11-03"]);
+        assert_eq!(guard.shifts[1].naps.len(), 1);
+
+        // Only the midnight hour counts, and every minute at most once per nap
+        assert_eq!(guard.total_sleeping(), 10 + 30);
+        assert_eq!(guard.asleep.get(&0), Some(&1));
+        assert_eq!(guard.asleep.get(&10), None);
+        assert_eq!(guard.asleep.get(&30), Some(&1));
+        assert!(guard.asleep.keys().all(|&m| m < 60));
+    }
+
+    #[test]
+    fn test_queries() {
+        let guards = gen_guards(TEST_INPUT);
+        let ts = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").expect("Bad timestamp");
+        assert_eq!(asleep_at(&guards, ts("1518-11-01 00:05")), vec![10]);
+        assert_eq!(
+            asleep_at(&guards, ts("1518-11-01 00:25")),
+            Vec::<u32>::new()
+        );
+        assert_eq!(asleep_at(&guards, ts("1518-11-04 00:45")), vec![99]);
+        assert_eq!(busiest_minute(&guards), (45, 4));
+
+        let guard_10 = guards.iter().find(|g| g.id == 10).expect("No guard 10");
+        assert_eq!(guard_10.nights(), 2);
+        assert_eq!(guard_10.longest_nap(), 25);
+        assert_eq!(guard_10.average_sleep(), 25.);
+        assert_eq!(guard_10.sleep_probability()[24], 1.);
+        assert_eq!(guard_10.sleep_probability()[5], 0.5);
+        assert_eq!(guard_10.sleep_probability()[0], 0.);
+
+        let guard_99 = guards.iter().find(|g| g.id == 99).expect("No guard 99");
+        assert_eq!(guard_99.nights(), 3);
+        assert_eq!(guard_99.longest_nap(), 10);
+        assert_eq!(guard_99.average_sleep(), 10.);
+    }
+
+    #[test]
+    fn test_strategies() {
+        let guards = gen_guards(TEST_INPUT);
+        let names: Vec<&str> = strategies().iter().map(|s| s.name()).collect();
+        assert_eq!(
+            names,
+            vec!["most_asleep", "most_frequent_minute", "most_consistent"]
+        );
+        assert!(strategy("unknown").is_none());
+
+        let most_asleep = strategy("most_asleep").expect("No most_asleep strategy");
+        // Every built-in strategy has a runner
+        assert_eq!(super::most_asleep(&guards), 240);
+        assert_eq!(super::most_frequent_minute(&guards), 4455);
+        assert_eq!(super::most_consistent(&guards), 240);
+        assert_eq!(solve(&guards, most_asleep.as_ref()), 240);
+        // Guard #10 sleeps on minute 24 on every night
+        assert_eq!(solve(&guards, &MostConsistent), 240);
+
+        // Ties go to the lowest ID, then to the earliest minute
+        let guards = gen_guards(
+            "[1518-11-01 00:00] Guard #7 begins shift
+[1518-11-01 00:10] falls asleep
+[1518-11-01 00:12] wakes up
+[1518-11-02 00:00] Guard #3 begins shift
+[1518-11-02 00:20] falls asleep
+[1518-11-02 00:22] wakes up",
+        );
+        assert_eq!(solve(&guards, &MostAsleep), 3 * 20);
+        assert_eq!(solve(&guards, &MostFrequentMinute), 3 * 20);
+        assert_eq!(solve(&guards, &MostConsistent), 3 * 20);
+    }
+
+    #[test]
+    fn test_exports() {
+        let guards = gen_guards(TEST_INPUT);
+
+        let matrix = sleep_matrix_csv(&guards);
+        let lines: Vec<&str> = matrix.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("guard,0,1,2,"));
+        assert!(lines[0].ends_with(",58,59"));
+        assert!(lines[1].starts_with("10,0,0,0,0,0,1,1,"));
+        assert!(lines[2].starts_with("99,"));
+        assert_eq!(lines[2].split(',').nth(46), Some("3"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&sleep_matrix_json(&guards)).expect("Invalid JSON");
+        assert_eq!(json[0]["guard"], 10);
+        assert_eq!(json[0]["minutes"][24], 2);
+        assert_eq!(json[1]["guard"], 99);
+        assert_eq!(json[1]["minutes"].as_array().map(|m| m.len()), Some(60));
+
+        let shifts = shifts_csv(&guards);
+        let lines: Vec<&str> = shifts.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("date,guard,0,1,"));
+        assert!(lines[1].starts_with("1518-11-01,10,0,0,0,0,0,1,"));
+        assert!(lines[2].starts_with("1518-11-03,10,"));
+        assert!(lines[3].starts_with("1518-11-02,99,"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&shifts_json(&guards)).expect("Invalid JSON");
+        assert_eq!(json[2]["date"], "1518-11-02");
+        assert_eq!(json[2]["guard"], 99);
+        assert_eq!(json[2]["asleep"][40], true);
+        assert_eq!(json[2]["asleep"][50], false);
+    }
+
     #[test]
     fn test_part_one() {
         let guards = gen_guards(TEST_INPUT);
@@ -1,53 +1,326 @@
-/// Reduces the polymer
-fn reduce(input: &str) -> String {
-    input
-        .chars()
-        .fold("".to_string(), |mut acc, i| match acc.pop() {
-            Some(ii) if (ii as u8) ^ (i as u8) != 32 => {
-                acc.push(ii);
-                acc.push(i);
-                acc
+use rayon::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Table of the reactions between units : which pairs of units annihilate
+/// each other, and which units may appear in a polymer at all.
+/// The default table makes every ASCII letter react with its opposite case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reactions {
+    /// Whether the units a and b react, at index a * 256 + b
+    pairs: Vec<bool>,
+    /// Whether each byte is a valid unit
+    alphabet: Vec<bool>,
+    /// Type of each unit : units linked by a chain of reactions share
+    /// the same type, represented by its lowest unit
+    types: Vec<u8>,
+}
+
+impl Reactions {
+    /// Creates a table without any unit nor reaction, rejecting every
+    /// polymer until units are added. `default` gives the letter rules.
+    pub fn empty() -> Self {
+        Reactions {
+            pairs: vec![false; 256 * 256],
+            alphabet: vec![false; 256],
+            types: (0..=255).collect(),
+        }
+    }
+
+    /// Allows the given unit in polymers, without making it react
+    pub fn add_unit(&mut self, unit: u8) {
+        self.alphabet[unit as usize] = true;
+    }
+
+    /// Makes the two given units react with each other
+    pub fn add_pair(&mut self, a: u8, b: u8) {
+        self.add_unit(a);
+        self.add_unit(b);
+        self.pairs[a as usize * 256 + b as usize] = true;
+        self.pairs[b as usize * 256 + a as usize] = true;
+
+        // Merges the types of both units
+        let (from, to) = (self.types[a as usize], self.types[b as usize]);
+        let (from, to) = (from.max(to), from.min(to));
+        self.types
+            .iter_mut()
+            .filter(|t| **t == from)
+            .for_each(|t| *t = to);
+    }
+
+    /// Checks if the two given units react
+    pub fn reacts(&self, a: u8, b: u8) -> bool {
+        self.pairs[a as usize * 256 + b as usize]
+    }
+
+    /// Checks if the given byte is a valid unit
+    pub fn is_unit(&self, unit: u8) -> bool {
+        self.alphabet[unit as usize]
+    }
+
+    /// Gets the type of a unit, as the lowest unit of said type
+    pub fn unit_type(&self, unit: u8) -> u8 {
+        self.types[unit as usize]
+    }
+
+    /// Checks if every unit reacts with one partner at most. Reductions
+    /// then give the same polymer whatever their order, and removing a unit
+    /// type commutes with them.
+    pub fn is_confluent(&self) -> bool {
+        (0..256).all(|a| {
+            self.pairs[a * 256..(a + 1) * 256]
+                .iter()
+                .filter(|&&r| r)
+                .count()
+                <= 1
+        })
+    }
+
+    /// Lists every unit type, as the lowest unit of each type
+    pub fn unit_types(&self) -> Vec<u8> {
+        (0..=255u8)
+            .filter(|&u| self.is_unit(u) && self.unit_type(u) == u)
+            .collect()
+    }
+}
+
+impl Default for Reactions {
+    /// Every ASCII letter reacts with its opposite case
+    fn default() -> Self {
+        let mut reactions = Reactions::empty();
+        (b'a'..=b'z').for_each(|u| reactions.add_pair(u, u.to_ascii_uppercase()));
+        reactions
+    }
+}
+
+/// Parses a rule file. Each line holds either two units separated
+/// by whitespace, which react with each other, or a single unit
+/// allowed in polymers without reacting. Units are single ASCII
+/// characters. Empty lines and lines starting with "//" are ignored.
+impl FromStr for Reactions {
+    type Err = Box<dyn Error>;
+
+    fn from_str(input: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reactions = Reactions::empty();
+        for (i, l) in input.lines().enumerate() {
+            let line = l.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
             }
-            None => {
-                acc.push(i);
-                acc
+
+            let units: Vec<&str> = line.split_whitespace().collect();
+            if let Some(u) = units.iter().find(|u| u.len() != 1 || !u.is_ascii()) {
+                return Err(format!("Line {}: invalid unit \"{}\"", i + 1, u).into());
             }
-            _ => acc,
-        })
+            match units.as_slice() {
+                [a] => reactions.add_unit(a.as_bytes()[0]),
+                [a, b] => reactions.add_pair(a.as_bytes()[0], b.as_bytes()[0]),
+                _ => return Err(format!("Line {}: expected one or two units", i + 1).into()),
+            }
+        }
+        Ok(reactions)
+    }
 }
 
-/// Removes all occurence of a given char and its capitalized version.
-/// Said char is specified as u8
-fn remove_specified(input: &str, spec: u8) -> String {
-    input
-        .chars()
-        .filter_map(|a| {
-            if a as u8 != spec && a as u8 + 32 != spec {
-                Some(a)
-            } else {
-                None
+/// Error raised when a polymer contains something else than a known unit
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidUnit {
+    /// Position (in bytes) of the invalid unit in the polymer
+    pub position: usize,
+    /// The invalid byte
+    pub unit: u8,
+}
+
+impl fmt::Display for InvalidUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid unit {:?} at position {}",
+            self.unit as char, self.position
+        )
+    }
+}
+
+impl Error for InvalidUnit {}
+
+/// Checks that the polymer is only made of units known to the table
+pub fn validate(input: &[u8], reactions: &Reactions) -> Result<(), InvalidUnit> {
+    match input.iter().position(|&u| !reactions.is_unit(u)) {
+        Some(position) => Err(InvalidUnit {
+            position,
+            unit: input[position],
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Reduces the polymer in place, using the start of the buffer as a stack
+/// of the units that did not react. The polymer must have been validated.
+fn reduce_in_place(polymer: &mut Vec<u8>, reactions: &Reactions) {
+    let mut top = 0;
+    for i in 0..polymer.len() {
+        let unit = polymer[i];
+        if top > 0 && reactions.reacts(polymer[top - 1], unit) {
+            top -= 1;
+        } else {
+            polymer[top] = unit;
+            top += 1;
+        }
+    }
+    polymer.truncate(top);
+}
+
+/// Reduces the polymer
+pub fn reduce(input: &[u8], reactions: &Reactions) -> Result<Vec<u8>, InvalidUnit> {
+    validate(input, reactions)?;
+    let mut polymer = input.to_vec();
+    reduce_in_place(&mut polymer, reactions);
+    Ok(polymer)
+}
+
+/// A single reaction that happened while reducing a polymer
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reaction {
+    /// Number of the reaction, starting at 1
+    pub step: usize,
+    /// Positions of both units in the original polymer
+    pub positions: (usize, usize),
+    /// Both units that reacted
+    pub units: (u8, u8),
+}
+
+impl fmt::Display for Reaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "step {}: {}{} at {} and {}",
+            self.step,
+            self.units.0 as char,
+            self.units.1 as char,
+            self.positions.0,
+            self.positions.1
+        )
+    }
+}
+
+/// Reduces the polymer, recording every reaction in the order it happens.
+/// Slower than `reduce`, as the stack holds the original positions.
+pub fn reduce_traced(
+    input: &[u8],
+    reactions: &Reactions,
+) -> Result<(Vec<u8>, Vec<Reaction>), InvalidUnit> {
+    validate(input, reactions)?;
+    let mut stack: Vec<usize> = vec![];
+    let mut trace: Vec<Reaction> = vec![];
+    for (i, &unit) in input.iter().enumerate() {
+        match stack.last() {
+            Some(&top) if reactions.reacts(input[top], unit) => {
+                stack.pop();
+                trace.push(Reaction {
+                    step: trace.len() + 1,
+                    positions: (top, i),
+                    units: (input[top], unit),
+                });
             }
+            _ => stack.push(i),
+        }
+    }
+    Ok((stack.into_iter().map(|i| input[i]).collect(), trace))
+}
+
+/// Replays a trace like the subject does, showing the polymer
+/// after each reaction : `dabAcCaCBAcCcaDA → dabAaCBAcCcaDA → ...`
+pub fn format_trace(input: &[u8], trace: &[Reaction]) -> String {
+    let mut alive = vec![true; input.len()];
+    let state = |alive: &[bool]| -> String {
+        input
+            .iter()
+            .zip(alive.iter())
+            .filter(|(_, &a)| a)
+            .map(|(&u, _)| u as char)
+            .collect()
+    };
+
+    let mut states = vec![state(&alive)];
+    trace.iter().for_each(|r| {
+        alive[r.positions.0] = false;
+        alive[r.positions.1] = false;
+        states.push(state(&alive));
+    });
+    states.join(" → ")
+}
+
+/// Removes all occurence of the type of the given unit, that is the
+/// unit and every unit it reacts with (its capitalized version by default)
+pub fn remove_specified(input: &[u8], spec: u8, reactions: &Reactions) -> Vec<u8> {
+    let spec_type = reactions.unit_type(spec);
+    input
+        .iter()
+        .filter(|&&a| reactions.unit_type(a) != spec_type)
+        .cloned()
+        .collect()
+}
+
+/// Computes, for every unit type, the length of the reduced polymer once
+/// said unit type is removed. The polymer must be validated, and reduced
+/// beforehand only with confluent `Reactions` : removing a unit type then
+/// commutes with the reactions already done.
+fn removal_lengths_reduced(reduced: &[u8], reactions: &Reactions) -> Vec<(u8, usize)> {
+    reactions
+        .unit_types()
+        .into_par_iter()
+        .map(|a| {
+            let mut polymer = remove_specified(reduced, a, reactions);
+            reduce_in_place(&mut polymer, reactions);
+            (a, polymer.len())
         })
         .collect()
 }
 
+/// Computes, for every unit type (as its lowest unit, in ascending order),
+/// the length of the fully reduced polymer once said unit type is removed.
+/// With confluent `Reactions`, the polymer is reduced once beforehand.
+pub fn removal_lengths(
+    input: &[u8],
+    reactions: &Reactions,
+) -> Result<Vec<(u8, usize)>, InvalidUnit> {
+    if reactions.is_confluent() {
+        Ok(removal_lengths_reduced(
+            &reduce(input, reactions)?,
+            reactions,
+        ))
+    } else {
+        validate(input, reactions)?;
+        Ok(removal_lengths_reduced(input, reactions))
+    }
+}
+
 /// Today's input was not trimmed!
 #[aoc_generator(day5)]
-fn generator(input: &str) -> String {
-    input.trim().to_string()
+fn generator(input: &str) -> Result<Vec<u8>, InvalidUnit> {
+    let polymer = input.trim().as_bytes();
+    validate(polymer, &Reactions::default())?;
+    Ok(polymer.to_vec())
 }
 
 /// Solves part one
 #[aoc(day5, part1)]
-fn part_one(input: &str) -> usize {
-    reduce(input).len()
+fn part_one(input: &[u8]) -> usize {
+    let mut polymer = input.to_vec();
+    reduce_in_place(&mut polymer, &Reactions::default());
+    polymer.len()
 }
 
 /// Solves part two
 #[aoc(day5, part2)]
-fn part_two(input: &str) -> usize {
-    ('a' as u8..='z' as u8)
-        .map(|a| reduce(&remove_specified(input, a)).len())
+fn part_two(input: &[u8]) -> usize {
+    let reactions = Reactions::default();
+    let mut polymer = input.to_vec();
+    reduce_in_place(&mut polymer, &reactions);
+    removal_lengths_reduced(&polymer, &reactions)
+        .into_iter()
+        .map(|(_, len)| len)
         .min()
         .expect("Could not find minimum")
 }
@@ -58,29 +331,149 @@ pub mod tests {
 
     #[test]
     fn day5_reduce() {
-        assert_eq!(reduce("aA"), "".to_string());
-        assert_eq!(reduce("abBA"), "".to_string());
-        assert_eq!(reduce("abAB"), "abAB".to_string());
-        assert_eq!(reduce("aabAAB"), "aabAAB".to_string());
+        assert_eq!(reduce(b"aA", &Reactions::default()), Ok(b"".to_vec()));
+        assert_eq!(reduce(b"abBA", &Reactions::default()), Ok(b"".to_vec()));
+        assert_eq!(reduce(b"abAB", &Reactions::default()), Ok(b"abAB".to_vec()));
+        assert_eq!(
+            reduce(b"aabAAB", &Reactions::default()),
+            Ok(b"aabAAB".to_vec())
+        );
+        assert_eq!(
+            reduce(b"dabAcCaCBAcCcaDA", &Reactions::default()),
+            Ok(b"dabCBAcaDA".to_vec())
+        );
+    }
+
+    #[test]
+    fn day5_reduce_invalid() {
+        assert_eq!(
+            reduce(b"ab@`", &Reactions::default()),
+            Err(InvalidUnit {
+                position: 2,
+                unit: b'@'
+            })
+        );
+        assert_eq!(
+            reduce("aé".as_bytes(), &Reactions::default()).map_err(|e| e.position),
+            Err(1)
+        );
+        assert!(generator("dabAcCaCBAcCcaDA\n").is_ok());
+        assert!(generator("dabAc CaCBAcCcaDA").is_err());
+    }
+
+    #[test]
+    fn day5_reduce_traced() {
+        let input = b"dabAcCaCBAcCcaDA";
+        let (polymer, trace) =
+            reduce_traced(input, &Reactions::default()).expect("Invalid polymer");
+        assert_eq!(polymer, b"dabCBAcaDA".to_vec());
+        assert_eq!(
+            trace,
+            vec![
+                Reaction {
+                    step: 1,
+                    positions: (4, 5),
+                    units: (b'c', b'C')
+                },
+                Reaction {
+                    step: 2,
+                    positions: (3, 6),
+                    units: (b'A', b'a')
+                },
+                Reaction {
+                    step: 3,
+                    positions: (10, 11),
+                    units: (b'c', b'C')
+                },
+            ]
+        );
+        assert_eq!(trace[1].to_string(), "step 2: Aa at 3 and 6");
+        assert_eq!(
+            format_trace(input, &trace),
+            "dabAcCaCBAcCcaDA → dabAaCBAcCcaDA → dabCBAcCcaDA → dabCBAcaDA"
+        );
     }
 
     #[test]
     fn day5_reduce_spec() {
         assert_eq!(
-            remove_specified("dabAcCaCBAcCcaDA", 'a' as u8),
-            "dbcCCBcCcD".to_string()
+            remove_specified(b"dabAcCaCBAcCcaDA", b'a', &Reactions::default()),
+            b"dbcCCBcCcD".to_vec()
         );
         assert_eq!(
-            remove_specified("dabAcCaCBAcCcaDA", 'b' as u8),
-            "daAcCaCAcCcaDA".to_string()
+            remove_specified(b"dabAcCaCBAcCcaDA", b'b', &Reactions::default()),
+            b"daAcCaCAcCcaDA".to_vec()
         );
         assert_eq!(
-            remove_specified("dabAcCaCBAcCcaDA", 'c' as u8),
-            "dabAaBAaDA".to_string()
+            remove_specified(b"dabAcCaCBAcCcaDA", b'c', &Reactions::default()),
+            b"dabAaBAaDA".to_vec()
         );
         assert_eq!(
-            remove_specified("dabAcCaCBAcCcaDA", 'd' as u8),
-            "abAcCaCBAcCcaA".to_string()
+            remove_specified(b"dabAcCaCBAcCcaDA", b'd', &Reactions::default()),
+            b"abAcCaCBAcCcaA".to_vec()
         );
     }
+
+    #[test]
+    fn day5_removal_lengths() {
+        let lengths =
+            removal_lengths(b"dabAcCaCBAcCcaDA", &Reactions::default()).expect("Invalid polymer");
+        assert_eq!(lengths.len(), 26);
+        assert_eq!(&lengths[..4], &[(b'A', 6), (b'B', 8), (b'C', 4), (b'D', 6)]);
+        assert_eq!(lengths[25], (b'Z', 10));
+        assert!(removal_lengths(b"da-b", &Reactions::default()).is_err());
+    }
+
+    #[test]
+    fn day5_reactions() {
+        let reactions: Reactions = "// digits cancel letters
+1 a
+2 b
+
+x
+a +"
+        .parse()
+        .expect("Failed to parse reactions");
+        assert!(reactions.reacts(b'1', b'a'));
+        assert!(reactions.reacts(b'a', b'1'));
+        assert!(!reactions.reacts(b'1', b'b'));
+        assert!(reactions.is_unit(b'x'));
+        assert!(!reactions.is_unit(b'A'));
+        assert_eq!(reactions.unit_types(), vec![b'+', b'2', b'x']);
+
+        assert_eq!(reduce(b"x1ab2x", &reactions), Ok(b"xx".to_vec()));
+        assert_eq!(reduce(b"+a1", &reactions), Ok(b"1".to_vec()));
+        assert!(reduce(b"aA", &reactions).is_err());
+        assert_eq!(
+            remove_specified(b"x1a+b2", b'1', &reactions),
+            b"xb2".to_vec()
+        );
+
+        assert!(Reactions::default().is_confluent());
+        assert!(!reactions.is_confluent());
+
+        // Without confluence, the unit type must be removed before reducing
+        let reactions: Reactions = "1 a\n2 a\n1 b\nx X"
+            .parse()
+            .expect("Failed to parse reactions");
+        let polymer = b"1xa2Xb";
+        assert_eq!(reduce(polymer, &reactions), Ok(vec![]));
+        assert_eq!(
+            reduce(&remove_specified(polymer, b'X', &reactions), &reactions),
+            Ok(b"2b".to_vec())
+        );
+        let lengths = removal_lengths(polymer, &reactions).expect("Invalid polymer");
+        assert!(lengths.contains(&(b'X', 2)));
+
+        assert!("ab".parse::<Reactions>().is_err());
+        assert!("a b c".parse::<Reactions>().is_err());
+        assert_eq!(Reactions::default().unit_types().len(), 26);
+    }
+
+    #[test]
+    fn day5_parts() {
+        let polymer = generator("dabAcCaCBAcCcaDA").expect("Invalid polymer");
+        assert_eq!(part_one(&polymer), 10);
+        assert_eq!(part_two(&polymer), 4);
+    }
 }
@@ -1,11 +1,13 @@
-use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use std::collections::{BTreeSet, VecDeque};
+use std::error::Error;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Point2 {
-    x: i32,
-    y: i32,
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Point2 {
@@ -16,6 +18,179 @@ impl Point2 {
     pub fn distance(&self, other: &Self) -> i32 {
         self.x.max(other.x) - self.x.min(other.x) + self.y.max(other.y) - self.y.min(other.y)
     }
+
+    /// Computes the distance to another point using the given `Metric`
+    pub fn distance_with(&self, other: &Self, metric: &dyn Metric) -> i64 {
+        metric.distance(
+            (i64::from(self.x) - i64::from(other.x)).abs(),
+            (i64::from(self.y) - i64::from(other.y)).abs(),
+        )
+    }
+}
+
+/// A distance along one axis, for a difference `d`.
+/// Weights must be positive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// `weight * d`
+    Linear(i64),
+    /// `weight * d²`
+    Quadratic(i64),
+}
+
+impl Axis {
+    /// The distance once the axis is scaled by the given factor
+    fn scaled(self, factor: i64) -> Axis {
+        match self {
+            Axis::Linear(w) => Axis::Linear(w * factor),
+            Axis::Quadratic(w) => Axis::Quadratic(w * factor * factor),
+        }
+    }
+}
+
+/// The shape of a `Metric`, which tells how far its regions extend
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Geometry {
+    /// `|u| + |v|`, up to a constant factor, where u and v are the given
+    /// independent linear forms `(a, b)` of the differences : `a * dx + b * dy`
+    Polyhedral((i64, i64), (i64, i64)),
+    /// `a * dx² + b * dy²`, with positive weights
+    Quadratic(i64, i64),
+}
+
+impl Geometry {
+    /// The shape once the x and y axes are scaled by the given factors
+    fn scaled(self, x: i64, y: i64) -> Geometry {
+        match self {
+            Geometry::Polyhedral(u, v) => {
+                Geometry::Polyhedral((u.0 * x, u.1 * y), (v.0 * x, v.1 * y))
+            }
+            Geometry::Quadratic(a, b) => Geometry::Quadratic(a * x * x, b * y * y),
+        }
+    }
+}
+
+/// A way of measuring the distance between two points
+pub trait Metric {
+    /// Computes the distance between two points, given the absolute
+    /// differences of their coordinates along x and y
+    fn distance(&self, dx: i64, dy: i64) -> i64;
+
+    /// The shape of the distance, used to find the infinite regions
+    fn geometry(&self) -> Geometry;
+
+    /// The distances along x and along y, if the distance is their sum,
+    /// i.e. `distance(dx, dy) == distance(dx, 0) + distance(0, dy)`
+    fn separable(&self) -> Option<(Axis, Axis)> {
+        None
+    }
+
+    /// The moves between neighbouring cells, if the distance between two
+    /// cells is the length of the shortest path made of such moves.
+    /// Enables the breadth-first computation of the regions.
+    fn neighbours(&self) -> Option<&'static [(i32, i32)]> {
+        None
+    }
+}
+
+/// The taxicab distance of the subject : `dx + dy`
+pub struct Manhattan;
+
+impl Metric for Manhattan {
+    fn distance(&self, dx: i64, dy: i64) -> i64 {
+        dx + dy
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::Polyhedral((1, 0), (0, 1))
+    }
+
+    fn separable(&self) -> Option<(Axis, Axis)> {
+        Some((Axis::Linear(1), Axis::Linear(1)))
+    }
+
+    fn neighbours(&self) -> Option<&'static [(i32, i32)]> {
+        Some(&[(-1, 0), (1, 0), (0, -1), (0, 1)])
+    }
+}
+
+/// The chessboard distance : `max(dx, dy)`
+pub struct Chebyshev;
+
+impl Metric for Chebyshev {
+    fn distance(&self, dx: i64, dy: i64) -> i64 {
+        dx.max(dy)
+    }
+
+    /// `max(dx, dy) == (|dx + dy| + |dx - dy|) / 2`
+    fn geometry(&self) -> Geometry {
+        Geometry::Polyhedral((1, 1), (1, -1))
+    }
+
+    fn neighbours(&self) -> Option<&'static [(i32, i32)]> {
+        Some(&[
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, -1),
+            (0, 1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+        ])
+    }
+}
+
+/// The squared euclidean distance : `dx² + dy²`.
+/// Squaring keeps the distances integral without changing the closest points.
+pub struct SquaredEuclidean;
+
+impl Metric for SquaredEuclidean {
+    fn distance(&self, dx: i64, dy: i64) -> i64 {
+        dx * dx + dy * dy
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::Quadratic(1, 1)
+    }
+
+    fn separable(&self) -> Option<(Axis, Axis)> {
+        Some((Axis::Quadratic(1), Axis::Quadratic(1)))
+    }
+}
+
+/// Any `Metric`, applied after scaling the x and y axes by the given weights
+pub struct Weighted<M: Metric> {
+    metric: M,
+    x: i64,
+    y: i64,
+}
+
+impl<M: Metric> Weighted<M> {
+    /// Creates a weighted metric. Weights must be positive : others
+    /// would not give a distance.
+    pub fn new(metric: M, x: i64, y: i64) -> Result<Self, Box<dyn Error>> {
+        if x <= 0 || y <= 0 {
+            return Err(format!("Weights must be positive, got ({}, {})", x, y).into());
+        }
+        Ok(Weighted { metric, x, y })
+    }
+}
+
+impl<M: Metric> Metric for Weighted<M> {
+    fn distance(&self, dx: i64, dy: i64) -> i64 {
+        self.metric.distance(dx * self.x, dy * self.y)
+    }
+
+    fn geometry(&self) -> Geometry {
+        self.metric.geometry().scaled(self.x, self.y)
+    }
+
+    fn separable(&self) -> Option<(Axis, Axis)> {
+        self.metric
+            .separable()
+            .map(|(x, y)| (x.scaled(self.x), y.scaled(self.y)))
+    }
 }
 
 impl FromStr for Point2 {
@@ -51,73 +226,1050 @@ pub fn generator(input: &str) -> Vec<Point2> {
         .collect()
 }
 
-#[aoc(day6, part1)]
-pub fn part_one(input: &[Point2]) -> i32 {
+/// Finds the index of the point closest to the given cell,
+/// or None if several points are at the same distance
+fn closest(input: &[Point2], cell: &Point2, metric: &dyn Metric) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    let mut best_distance = i64::MAX;
+    input.iter().enumerate().for_each(|(i, p)| {
+        let d = p.distance_with(cell, metric);
+        if d < best_distance {
+            best = Some(i);
+            best_distance = d;
+        } else if d == best_distance {
+            best = None;
+        }
+    });
+    best
+}
+
+/// Finds the owner of every cell of the bounding box, column by column,
+/// by checking every point for every cell. None stands for a tie.
+fn owners_scan(
+    input: &[Point2],
+    bbox: (i32, i32, i32, i32),
+    metric: &dyn Metric,
+) -> Vec<Option<usize>> {
+    (bbox.0..=bbox.2)
+        .flat_map(|x| (bbox.1..=bbox.3).map(move |y| Point2::new(x, y)))
+        .map(|cell| closest(input, &cell, metric))
+        .collect()
+}
+
+/// Finds the owner of every cell of the bounding box, column by column,
+/// and its distance to the closest points, by flooding the box from all
+/// the points at once (see `flood_fill`)
+fn owners_flood(
+    input: &[Point2],
+    bbox: (i32, i32, i32, i32),
+    moves: &[(i32, i32)],
+) -> (Vec<Option<usize>>, Vec<u32>) {
+    let (w, h) = (
+        (bbox.2 - bbox.0 + 1) as usize,
+        (bbox.3 - bbox.1 + 1) as usize,
+    );
+    let idx = |x: i32, y: i32| (x - bbox.0) as usize * h + (y - bbox.1) as usize;
+    let sources: Vec<usize> = input.iter().map(|p| idx(p.x, p.y)).collect();
+    flood_fill(w * h, &sources, |cell, next| {
+        let (x, y) = (bbox.0 + (cell / h) as i32, bbox.1 + (cell % h) as i32);
+        moves
+            .iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(x, y)| x >= bbox.0 && x <= bbox.2 && y >= bbox.1 && y <= bbox.3)
+            .for_each(|(x, y)| next.push(idx(x, y)));
+    })
+}
+
+/// Owner of a cell during the flood fill
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Owner {
+    Unreached,
+    Point(usize),
+    Tie,
+}
+
+/// Finds the owner of every cell of a grid, given the cell of every point,
+/// and its distance in steps to the closest points,
+/// by flooding the grid from all the points at once, one step at a time.
+/// The closest points of a cell are those of its neighbours one step closer,
+/// so a cell reached from different owners (or from a tie) is a tie.
+/// Shortest paths between two cells of a box never leave it.
+fn flood_fill(
+    size: usize,
+    sources: &[usize],
+    neighbours: impl Fn(usize, &mut Vec<usize>),
+) -> (Vec<Option<usize>>, Vec<u32>) {
+    let mut owners = vec![Owner::Unreached; size];
+    let mut distances = vec![0u32; size];
+
+    let mut frontier: Vec<usize> = vec![];
+    sources.iter().enumerate().for_each(|(i, &cell)| {
+        owners[cell] = match owners[cell] {
+            Owner::Unreached => {
+                frontier.push(cell);
+                Owner::Point(i)
+            }
+            _ => Owner::Tie,
+        };
+    });
+
+    let mut distance = 0;
+    let mut around: Vec<usize> = vec![];
+    while !frontier.is_empty() {
+        distance += 1;
+        let mut next: Vec<usize> = vec![];
+        for cell in frontier {
+            let owner = owners[cell];
+            around.clear();
+            neighbours(cell, &mut around);
+            for &n in &around {
+                match owners[n] {
+                    Owner::Unreached => {
+                        owners[n] = owner;
+                        distances[n] = distance;
+                        next.push(n);
+                    }
+                    other if distances[n] == distance && other != owner => owners[n] = Owner::Tie,
+                    _ => (),
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    let owners = owners
+        .into_iter()
+        .map(|o| match o {
+            Owner::Point(i) => Some(i),
+            _ => None,
+        })
+        .collect();
+    (owners, distances)
+}
+
+/// The region of a point : the cells closer to it than to any other point
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    /// The point owning the region
+    pub point: Point2,
+    /// Cells of the region, within the frame scanned by `voronoi`
+    pub cells: Vec<Point2>,
+    /// Whether the region is infinite
+    pub infinite: bool,
+    /// Bounding box of `cells` as (x_min, y_min, x_max, y_max),
+    /// None if the region is empty (the point is duplicated)
+    pub bbox: Option<(i32, i32, i32, i32)>,
+}
+
+impl Region {
+    /// Area of the region, or None if it is infinite
+    pub fn area(&self) -> Option<usize> {
+        if self.infinite {
+            None
+        } else {
+            Some(self.cells.len())
+        }
+    }
+}
+
+/// The partition of the bounding box of the points into their regions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Voronoi {
+    /// The region of every point, in the order of the input
+    pub regions: Vec<Region>,
+    /// Cells of the frame at the same distance of several points, owned by no one
+    pub ties: Vec<Point2>,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Rounds `n / d` down
+fn floor_div(n: i64, d: i64) -> i64 {
+    if d < 0 {
+        (-n).div_euclid(-d)
+    } else {
+        n.div_euclid(d)
+    }
+}
+
+/// How `voronoi` tells the infinite regions apart
+enum Unbounded {
+    /// Regions owning a cell where a linear form `u` or `v` reaches or
+    /// passes its extreme values over the points, given as (min, max)
+    Outside {
+        u: (i64, i64),
+        v: (i64, i64),
+        u_range: (i64, i64),
+        v_range: (i64, i64),
+    },
+    /// Regions whose point is on the border of the convex hull
+    Hull(Vec<bool>),
+}
+
+impl Unbounded {
+    fn outside(&self, cell: &Point2) -> bool {
+        match self {
+            Unbounded::Outside {
+                u,
+                v,
+                u_range,
+                v_range,
+            } => {
+                let (x, y) = (i64::from(cell.x), i64::from(cell.y));
+                let (cu, cv) = (u.0 * x + u.1 * y, v.0 * x + v.1 * y);
+                cu <= u_range.0 || cu >= u_range.1 || cv <= v_range.0 || cv >= v_range.1
+            }
+            Unbounded::Hull(_) => false,
+        }
+    }
+}
+
+/// Finds a frame holding every finite region for a polyhedral metric.
+/// Past the extreme value of `u` over the points, moving a cell along a
+/// step that keeps `v` increases the distance to every point by the same
+/// amount, so the cell keeps its owner : the region is infinite. The other
+/// way round, any outer cell can be moved back that way to within a step
+/// of the extreme value. Finite regions stay strictly between the extreme
+/// values, so the frame covers them, padded by a step minus one.
+fn polyhedral_frame(
+    input: &[Point2],
+    u: (i64, i64),
+    v: (i64, i64),
+) -> ((i32, i32, i32, i32), Unbounded) {
+    let det = u.0 * v.1 - u.1 * v.0;
+    assert!(det != 0, "Degenerate metric");
+    let range = |f: (i64, i64)| {
+        let values = input
+            .iter()
+            .map(|p| f.0 * i64::from(p.x) + f.1 * i64::from(p.y));
+        (values.clone().min().unwrap_or(0), values.max().unwrap_or(0))
+    };
+    let (u_range, v_range) = (range(u), range(v));
+    let pad_u = det.abs() / gcd(v.0, v.1) - 1;
+    let pad_v = det.abs() / gcd(u.0, u.1) - 1;
+
+    // Corners of the padded range, back to x and y
     let bbox = bounding_box(input);
-    let final_bounds = (bbox.0 - 400, bbox.1 - 400, bbox.2 + 400, bbox.3 + 400);
+    let mut frame = (
+        i64::from(bbox.0),
+        i64::from(bbox.1),
+        i64::from(bbox.2),
+        i64::from(bbox.3),
+    );
+    for &cu in &[u_range.0 - pad_u, u_range.1 + pad_u] {
+        for &cv in &[v_range.0 - pad_v, v_range.1 + pad_v] {
+            let (x, y) = (v.1 * cu - u.1 * cv, u.0 * cv - v.0 * cu);
+            frame.0 = frame.0.min(floor_div(x, det));
+            frame.1 = frame.1.min(floor_div(y, det));
+            frame.2 = frame.2.max(-floor_div(-x, det));
+            frame.3 = frame.3.max(-floor_div(-y, det));
+        }
+    }
+    let frame = (
+        frame.0 as i32,
+        frame.1 as i32,
+        frame.2 as i32,
+        frame.3 as i32,
+    );
+    (
+        frame,
+        Unbounded::Outside {
+            u,
+            v,
+            u_range,
+            v_range,
+        },
+    )
+}
 
-    let input_filtered: Vec<Point2> = input
+/// Checks if a point is on the border of the convex hull of all the points :
+/// all of them then fit in a closed half-plane whose edge goes through it,
+/// i.e. the directions to the other points leave a gap of half a turn or more
+fn on_hull(input: &[Point2], i: usize) -> bool {
+    let p = &input[i];
+    let mut dirs: Vec<(i64, i64)> = input
         .iter()
-        .filter_map(|p| {
-            if p.x == bbox.0 || p.x == bbox.2 || p.y == bbox.1 || p.y == bbox.3 {
-                None
-            } else {
-                Some(p.clone())
-            }
+        .map(|q| (i64::from(q.x - p.x), i64::from(q.y - p.y)))
+        .filter(|&d| d != (0, 0))
+        .collect();
+    if dirs.is_empty() {
+        return true;
+    }
+    let cross = |a: (i64, i64), b: (i64, i64)| a.0 * b.1 - a.1 * b.0;
+    let dot = |a: (i64, i64), b: (i64, i64)| a.0 * b.0 + a.1 * b.1;
+    let half = |d: (i64, i64)| d.1 < 0 || (d.1 == 0 && d.0 < 0);
+    // Sorts the directions by angle
+    dirs.sort_by(|&a, &b| half(a).cmp(&half(b)).then(0.cmp(&cross(a, b))));
+    if dirs
+        .iter()
+        .all(|&d| cross(dirs[0], d) == 0 && dot(dirs[0], d) > 0)
+    {
+        return true;
+    }
+    (0..dirs.len()).any(|k| {
+        let (a, b) = (dirs[k], dirs[(k + 1) % dirs.len()]);
+        cross(a, b) < 0 || (cross(a, b) == 0 && dot(a, b) < 0)
+    })
+}
+
+/// Finds the extent (x_min, y_min, x_max, y_max) of the finite region of a
+/// point for the quadratic metric `a * dx² + b * dy²`. The region is the
+/// intersection of half-planes `A * x + B * y < C`, one per other point,
+/// hence convex : columns are scanned on both sides of the point until the
+/// region, seen as a set of real points, vanishes.
+fn quadratic_extent(input: &[Point2], i: usize, (a, b): (i64, i64)) -> (i32, i32, i32, i32) {
+    let p = &input[i];
+    let (px, py) = (i128::from(p.x), i128::from(p.y));
+    let (a, b) = (i128::from(a), i128::from(b));
+    let half_planes: Vec<(i128, i128, i128)> = input
+        .iter()
+        .filter(|q| *q != p)
+        .map(|q| {
+            let (qx, qy) = (i128::from(q.x), i128::from(q.y));
+            (
+                2 * a * (qx - px),
+                2 * b * (qy - py),
+                a * (qx * qx - px * px) + b * (qy * qy - py * py),
+            )
         })
         .collect();
 
-    let mut hash_map: FnvHashMap<Point2, i32> = FnvHashMap::default();
-    (final_bounds.0..=final_bounds.2)
-        .map(|x| (final_bounds.1..=final_bounds.3).map(move |y| Point2::new(x, y)))
-        .flat_map(|i| i.into_iter())
+    // Bounds of the column, as fractions (numerator, positive denominator),
+    // or None if it misses the region
+    let column = |x: i128| -> Option<(i128, i128)> {
+        let mut lo: Option<(i128, i128)> = None;
+        let mut hi: Option<(i128, i128)> = None;
+        for &(ca, cb, cc) in &half_planes {
+            let rhs = cc - ca * x;
+            if cb > 0 {
+                if hi.is_none_or(|h| rhs * h.1 < h.0 * cb) {
+                    hi = Some((rhs, cb));
+                }
+            } else if cb < 0 {
+                if lo.is_none_or(|l| -rhs * l.1 > l.0 * -cb) {
+                    lo = Some((-rhs, -cb));
+                }
+            } else if rhs < 0 {
+                return None;
+            }
+        }
+        let (lo, hi) = (lo.expect("Unbounded region"), hi.expect("Unbounded region"));
+        if lo.0 * hi.1 > hi.0 * lo.1 {
+            None
+        } else {
+            let ceil = -(-lo.0).div_euclid(lo.1);
+            Some((ceil, hi.0.div_euclid(hi.1)))
+        }
+    };
+
+    let mut extent = (p.x, p.y, p.x, p.y);
+    for &(start, dir) in &[(px, 1), (px - 1, -1)] {
+        let mut x = start;
+        while let Some((lo, hi)) = column(x) {
+            if lo <= hi {
+                extent = (
+                    extent.0.min(x as i32),
+                    extent.1.min(lo as i32),
+                    extent.2.max(x as i32),
+                    extent.3.max(hi as i32),
+                );
+            }
+            x += dir;
+        }
+    }
+    extent
+}
+
+/// Finds a frame holding every finite region for a quadratic metric. A
+/// region is infinite exactly when its point is on the convex hull, and
+/// the finite ones may leave the bounding box.
+fn quadratic_frame(input: &[Point2], a: i64, b: i64) -> ((i32, i32, i32, i32), Unbounded) {
+    let hull: Vec<bool> = (0..input.len()).map(|i| on_hull(input, i)).collect();
+    let mut frame = bounding_box(input);
+    (0..input.len())
+        .filter(|&i| !hull[i] && input.iter().filter(|q| **q == input[i]).count() == 1)
         .for_each(|i| {
-            let (point, _, count) = input.iter().map(|p| (p, p.distance(&i))).fold(
-                (Point2::new(0, 0), 100000, 0),
-                |acc, i| match i.1 {
-                    x if x < acc.1 => (i.0.clone(), i.1, 1),
-                    x if x == acc.1 => (i.0.clone(), i.1, acc.2 + 1),
-                    _ => acc,
-                },
+            let e = quadratic_extent(input, i, (a, b));
+            frame = (
+                frame.0.min(e.0),
+                frame.1.min(e.1),
+                frame.2.max(e.2),
+                frame.3.max(e.3),
             );
-            if count == 1 {
-                let p_val = hash_map.entry(point).or_insert(0);
-                *p_val += 1;
+        });
+    (frame, Unbounded::Hull(hull))
+}
+
+/// Computes the region of every point, and the tie cells, within a frame
+/// holding every finite region : the bounding box for the Manhattan metric,
+/// enlarged for the others. Infinite regions are found exactly, according
+/// to the `Geometry` of the metric :
+/// - polyhedral metrics (Manhattan, Chebyshev, weighted or not) are proportional
+///   to the sum of the absolute values of two linear forms, and a region is
+///   infinite when it owns a cell where one of them reaches its extreme
+///   values over the points (see `polyhedral_frame`)
+/// - with quadratic metrics (squared euclidean, weighted or not), a region
+///   is infinite when its point is on the convex hull of the points
+///
+/// Duplicated points own no cell, hence have empty, finite regions.
+pub fn voronoi(input: &[Point2], metric: &dyn Metric) -> Voronoi {
+    let mut regions: Vec<Region> = input
+        .iter()
+        .map(|p| Region {
+            point: p.clone(),
+            cells: vec![],
+            infinite: false,
+            bbox: None,
+        })
+        .collect();
+    let mut ties: Vec<Point2> = vec![];
+    if input.is_empty() {
+        return Voronoi { regions, ties };
+    }
+
+    let (frame, unbounded) = match metric.geometry() {
+        Geometry::Polyhedral(u, v) => polyhedral_frame(input, u, v),
+        Geometry::Quadratic(a, b) => quadratic_frame(input, a, b),
+    };
+    let owners = match metric.neighbours() {
+        Some(moves) => owners_flood(input, frame, moves).0,
+        None => owners_scan(input, frame, metric),
+    };
+    (frame.0..=frame.2)
+        .flat_map(|x| (frame.1..=frame.3).map(move |y| Point2::new(x, y)))
+        .zip(owners)
+        .for_each(|(cell, owner)| match owner {
+            Some(owner) => {
+                let region = &mut regions[owner];
+                region.infinite |= unbounded.outside(&cell);
+                region.bbox = Some(match region.bbox {
+                    Some(b) => (
+                        b.0.min(cell.x),
+                        b.1.min(cell.y),
+                        b.2.max(cell.x),
+                        b.3.max(cell.y),
+                    ),
+                    None => (cell.x, cell.y, cell.x, cell.y),
+                });
+                region.cells.push(cell);
             }
+            None => ties.push(cell),
         });
+    if let Unbounded::Hull(hull) = unbounded {
+        regions
+            .iter_mut()
+            .zip(hull)
+            .for_each(|(r, on_hull)| r.infinite = on_hull && !r.cells.is_empty());
+    }
+    Voronoi { regions, ties }
+}
 
-    *hash_map
+/// Computes the area of the region of every point, or None if said
+/// region is infinite (see `voronoi`)
+pub fn areas(input: &[Point2], metric: &dyn Metric) -> Vec<Option<usize>> {
+    voronoi(input, metric)
+        .regions
         .iter()
-        .filter_map(|(k, v)| {
-            if input_filtered.iter().filter(|p| *p == k).count() == 1 {
-                if *v < 10000 {
-                    Some(v)
-                } else {
-                    None
+        .map(|r| r.area())
+        .collect()
+}
+
+#[aoc(day6, part1)]
+pub fn part_one(input: &[Point2]) -> usize {
+    areas(input, &Manhattan)
+        .into_iter()
+        .flatten()
+        .max()
+        .expect("Could not find max value")
+}
+
+/// The regions of a set of points, using the Manhattan distance of the
+/// subject, kept up to date through point insertions and removals.
+/// Cells are tracked within a frame containing every point : regions that
+/// own a cell on its border are infinite, the others fit inside it.
+#[derive(Default)]
+pub struct LiveVoronoi {
+    /// Points by ID, None once removed
+    points: Vec<Option<Point2>>,
+    /// Tracked cells as (x_min, y_min, x_max, y_max)
+    frame: Option<(i32, i32, i32, i32)>,
+    /// ID of the point owning each cell, None for ties
+    owners: Vec<Option<usize>>,
+    /// Distance of each cell to its closest points
+    distances: Vec<i64>,
+    /// Number of cells owned by each point
+    cells: Vec<usize>,
+    /// Number of cells owned by each point on the border of the frame
+    border: Vec<usize>,
+}
+
+impl LiveVoronoi {
+    /// Creates a `LiveVoronoi` from the given points, whose IDs are their indices
+    pub fn new(input: &[Point2]) -> Self {
+        let mut live = LiveVoronoi {
+            points: input.iter().cloned().map(Some).collect(),
+            ..Default::default()
+        };
+        live.rebuild(if input.is_empty() {
+            None
+        } else {
+            Some(bounding_box(input))
+        });
+        live
+    }
+
+    /// Gets a point using its ID
+    pub fn get(&self, id: usize) -> Option<&Point2> {
+        self.points.get(id).and_then(|p| p.as_ref())
+    }
+
+    /// Area of the region of a point, or None if it is infinite.
+    /// Returns None as well for unknown IDs
+    pub fn area(&self, id: usize) -> Option<usize> {
+        self.get(id)?;
+        if self.border[id] > 0 {
+            None
+        } else {
+            Some(self.cells[id])
+        }
+    }
+
+    /// Areas of the regions of all the points, by ID (see `area`)
+    pub fn areas(&self) -> Vec<(usize, Option<usize>)> {
+        (0..self.points.len())
+            .filter(|&id| self.points[id].is_some())
+            .map(|id| (id, self.area(id)))
+            .collect()
+    }
+
+    /// Index of a cell of the frame, if it is inside
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let f = self.frame?;
+        if x < f.0 || x > f.2 || y < f.1 || y > f.3 {
+            None
+        } else {
+            Some((x - f.0) as usize * (f.3 - f.1 + 1) as usize + (y - f.1) as usize)
+        }
+    }
+
+    fn on_border(&self, x: i32, y: i32) -> bool {
+        self.frame
+            .is_some_and(|f| x == f.0 || x == f.2 || y == f.1 || y == f.3)
+    }
+
+    /// Recomputes every cell of the given frame from scratch
+    fn rebuild(&mut self, frame: Option<(i32, i32, i32, i32)>) {
+        self.frame = frame;
+        self.cells = vec![0; self.points.len()];
+        self.border = vec![0; self.points.len()];
+        let f = match frame {
+            Some(f) => f,
+            None => {
+                self.owners.clear();
+                self.distances.clear();
+                return;
+            }
+        };
+
+        let ids: Vec<usize> = (0..self.points.len())
+            .filter(|&id| self.points[id].is_some())
+            .collect();
+        let live: Vec<Point2> = ids
+            .iter()
+            .filter_map(|&id| self.points[id].clone())
+            .collect();
+        let (owners, distances) = owners_flood(&live, f, &[(-1, 0), (1, 0), (0, -1), (0, 1)]);
+        self.owners = owners.into_iter().map(|o| o.map(|i| ids[i])).collect();
+        self.distances = if live.is_empty() {
+            vec![i64::MAX; self.owners.len()]
+        } else {
+            distances.into_iter().map(i64::from).collect()
+        };
+
+        let h = (f.3 - f.1 + 1) as usize;
+        for (cell, owner) in self.owners.iter().enumerate() {
+            if let Some(owner) = *owner {
+                let (x, y) = (f.0 + (cell / h) as i32, f.1 + (cell % h) as i32);
+                self.cells[owner] += 1;
+                if self.on_border(x, y) {
+                    self.border[owner] += 1;
+                }
+            }
+        }
+    }
+
+    /// Visits the cells around the point, as long as `visit` accepts them.
+    /// The cells closer to a point than to any other one are reachable
+    /// from it through such cells, along the shortest paths.
+    fn spread(&mut self, from: &Point2, mut visit: impl FnMut(&mut Self, i32, i32) -> bool) {
+        let mut seen: FnvHashSet<(i32, i32)> = FnvHashSet::default();
+        let mut queue: VecDeque<(i32, i32)> = VecDeque::new();
+        seen.insert((from.x, from.y));
+        queue.push_back((from.x, from.y));
+        while let Some((x, y)) = queue.pop_front() {
+            if !visit(self, x, y) {
+                continue;
+            }
+            for (nx, ny) in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if self.index(*nx, *ny).is_some() && seen.insert((*nx, *ny)) {
+                    queue.push_back((*nx, *ny));
                 }
+            }
+        }
+    }
+
+    /// Gives the cell to its new owner, updating the statistics of both
+    /// owners, and records the regions that changed
+    fn assign(&mut self, x: i32, y: i32, owner: Option<usize>, changed: &mut BTreeSet<usize>) {
+        let cell = self.index(x, y).expect("Cell outside of the frame");
+        let border = self.on_border(x, y);
+        if let Some(old) = self.owners[cell] {
+            self.cells[old] -= 1;
+            if border {
+                self.border[old] -= 1;
+            }
+            changed.insert(old);
+        }
+        if let Some(new) = owner {
+            self.cells[new] += 1;
+            if border {
+                self.border[new] += 1;
+            }
+            changed.insert(new);
+        }
+        self.owners[cell] = owner;
+    }
+
+    /// Areas of the given regions, leaving out the removed points
+    fn report(&self, changed: BTreeSet<usize>) -> Vec<(usize, Option<usize>)> {
+        changed
+            .into_iter()
+            .filter(|&id| self.points[id].is_some())
+            .map(|id| (id, self.area(id)))
+            .collect()
+    }
+
+    /// Adds a point. Returns its ID, and the areas of the regions that
+    /// changed, including its own. Only the cells at least as close to the
+    /// new point as to their owner are examined, unless the point is
+    /// outside of the frame : the frame then grows and every cell is
+    /// computed again.
+    pub fn insert(&mut self, point: Point2) -> (usize, Vec<(usize, Option<usize>)>) {
+        let id = self.points.len();
+        self.points.push(Some(point.clone()));
+        self.cells.push(0);
+        self.border.push(0);
+
+        if self.index(point.x, point.y).is_none() {
+            let frame = match self.frame {
+                Some(f) => (
+                    f.0.min(point.x),
+                    f.1.min(point.y),
+                    f.2.max(point.x),
+                    f.3.max(point.y),
+                ),
+                None => (point.x, point.y, point.x, point.y),
+            };
+            self.rebuild(Some(frame));
+            return (id, self.areas());
+        }
+
+        let mut changed = BTreeSet::new();
+        changed.insert(id);
+        self.spread(&point, |live, x, y| {
+            let cell = live.index(x, y).expect("Cell outside of the frame");
+            let d = point.distance_with(&Point2::new(x, y), &Manhattan);
+            if d < live.distances[cell] {
+                live.distances[cell] = d;
+                live.assign(x, y, Some(id), &mut changed);
+                true
+            } else if d == live.distances[cell] {
+                live.assign(x, y, None, &mut changed);
+                true
             } else {
+                false
+            }
+        });
+        (id, self.report(changed))
+    }
+
+    /// Removes a point. Returns None if there is no point with this ID,
+    /// or the areas of the regions that changed. Only the cells the point
+    /// was closest to (alone or tied) are examined.
+    pub fn remove(&mut self, id: usize) -> Option<Vec<(usize, Option<usize>)>> {
+        let point = self.points.get_mut(id)?.take()?;
+        let ids: Vec<usize> = (0..self.points.len())
+            .filter(|&i| self.points[i].is_some())
+            .collect();
+        let points: Vec<Point2> = ids.iter().filter_map(|&i| self.points[i].clone()).collect();
+        let mut changed = BTreeSet::new();
+        self.spread(&point, |live, x, y| {
+            let cell = live.index(x, y).expect("Cell outside of the frame");
+            let here = Point2::new(x, y);
+            if point.distance_with(&here, &Manhattan) != live.distances[cell] {
+                return false;
+            }
+            live.distances[cell] = points
+                .iter()
+                .map(|p| p.distance_with(&here, &Manhattan))
+                .min()
+                .unwrap_or(i64::MAX);
+            let owner = if points.is_empty() {
                 None
+            } else {
+                closest(&points, &here, &Manhattan).map(|i| ids[i])
+            };
+            live.assign(x, y, owner, &mut changed);
+            true
+        });
+        Some(self.report(changed))
+    }
+}
+
+/// Sums of the distances along one axis to all the given coordinates,
+/// computed in O(log n) per position from prefix sums of the sorted
+/// coordinates. The sum is convex, and minimal between the extreme ones.
+struct AxisSums {
+    coords: Vec<i64>,
+    /// Sum of the first i coordinates, for every i
+    prefix: Vec<i64>,
+    /// Sum of the squared coordinates
+    squares: i64,
+    axis: Axis,
+}
+
+impl AxisSums {
+    fn new(mut coords: Vec<i64>, axis: Axis) -> Self {
+        coords.sort();
+        let mut prefix = vec![0];
+        coords
+            .iter()
+            .for_each(|c| prefix.push(prefix[prefix.len() - 1] + c));
+        let squares = coords.iter().map(|c| c * c).sum();
+        AxisSums {
+            coords,
+            prefix,
+            squares,
+            axis,
+        }
+    }
+
+    /// Sum of the distances from the given position
+    fn at(&self, x: i64) -> i64 {
+        let n = self.coords.len();
+        let total = self.prefix[n];
+        match self.axis {
+            Axis::Linear(w) => {
+                let k = self.coords.partition_point(|&c| c <= x);
+                let below = x * k as i64 - self.prefix[k];
+                let above = total - self.prefix[k] - x * (n - k) as i64;
+                w * (below + above)
             }
-        })
-        .max()
-        .expect("Could not find max value")
+            Axis::Quadratic(w) => w * (n as i64 * x * x - 2 * x * total + self.squares),
+        }
+    }
+
+    /// A position where the sum is minimal
+    fn argmin(&self) -> i64 {
+        let (mut lo, mut hi) = (self.coords[0], self.coords[self.coords.len() - 1]);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.at(mid + 1) >= self.at(mid) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    /// Starting from a position whose sum is below `limit`, finds the last
+    /// one in the given direction that stays below, doubling then halving
+    /// the step
+    fn edge(&self, from: i64, dir: i64, limit: i64) -> i64 {
+        let mut step = 1;
+        while self.at(from + dir * step) < limit {
+            step *= 2;
+        }
+        let (mut lo, mut hi) = (step / 2, step - 1);
+        while lo < hi {
+            let mid = lo + (hi - lo + 1) / 2;
+            if self.at(from + dir * mid) < limit {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        from + dir * lo
+    }
+
+    /// The sums at every position where they are below `limit`. Those
+    /// positions form an interval, that may extend beyond the bounding box.
+    fn below(&self, limit: i64) -> Vec<i64> {
+        let min = self.argmin();
+        if self.at(min) >= limit {
+            return vec![];
+        }
+        let (first, last) = (self.edge(min, -1, limit), self.edge(min, 1, limit));
+        (first..=last).map(|x| self.at(x)).collect()
+    }
+}
+
+/// Counts the positions whose total distance to all the points is below the
+/// threshold, when said distance is the sum of a distance along every axis.
+/// Each axis is scanned separately, as far as needed.
+fn separable_count(axes: &[AxisSums], threshold: i64) -> usize {
+    let mins: Vec<i64> = axes.iter().map(|a| a.at(a.argmin())).collect();
+    let total_min: i64 = mins.iter().sum();
+    let mut sums: Vec<Vec<i64>> = axes
+        .iter()
+        .zip(&mins)
+        .map(|(a, min)| a.below(threshold - (total_min - min)))
+        .collect();
+
+    // Partial sums over the first axes that can still stay below the threshold
+    let mut last = sums.pop().unwrap_or_default();
+    let mut rest_min = total_min;
+    let mut partials: Vec<i64> = vec![0];
+    for (axis_sums, min) in sums.iter().zip(&mins) {
+        rest_min -= min;
+        partials = partials
+            .iter()
+            .flat_map(|p| axis_sums.iter().map(move |s| p + s))
+            .filter(|s| s + rest_min < threshold)
+            .collect();
+    }
+    last.sort();
+    partials
+        .iter()
+        .map(|p| last.partition_point(|s| p + s < threshold))
+        .sum()
+}
+
+/// Counts the cells whose total distance to all the points is below the
+/// threshold, for a separable `Metric` (see `separable_count`)
+fn separable_safe_region_size(input: &[Point2], (x, y): (Axis, Axis), threshold: i64) -> usize {
+    let xs: Vec<i64> = input.iter().map(|p| i64::from(p.x)).collect();
+    let ys: Vec<i64> = input.iter().map(|p| i64::from(p.y)).collect();
+    separable_count(&[AxisSums::new(xs, x), AxisSums::new(ys, y)], threshold)
+}
+
+/// Counts the cells whose total distance to all the points is below the
+/// threshold, for any `Metric` : scans the bounding box, then rings around
+/// it until one is empty. The total distance is convex, so the safe region
+/// is convex and can't reappear after an empty ring.
+fn scanned_safe_region_size(input: &[Point2], metric: &dyn Metric, threshold: i64) -> usize {
+    let bbox = bounding_box(input);
+    let safe = |x: i32, y: i32| {
+        let cell = Point2::new(x, y);
+        input
+            .iter()
+            .map(|p| p.distance_with(&cell, metric))
+            .sum::<i64>()
+            < threshold
+    };
+
+    let mut count = (bbox.0..=bbox.2)
+        .flat_map(|x| (bbox.1..=bbox.3).map(move |y| (x, y)))
+        .filter(|&(x, y)| safe(x, y))
+        .count();
+    for r in 1.. {
+        let (x0, y0, x1, y1) = (bbox.0 - r, bbox.1 - r, bbox.2 + r, bbox.3 + r);
+        let ring = (x0..=x1)
+            .flat_map(|x| vec![(x, y0), (x, y1)])
+            .chain((y0 + 1..y1).flat_map(|y| vec![(x0, y), (x1, y)]))
+            .filter(|&(x, y)| safe(x, y))
+            .count();
+        if ring == 0 {
+            break;
+        }
+        count += ring;
+    }
+    count
+}
+
+/// Counts the cells whose total distance to all the points,
+/// using the given `Metric`, is below the threshold
+pub fn safe_region_size(input: &[Point2], metric: &dyn Metric, threshold: i64) -> usize {
+    if input.is_empty() {
+        0
+    } else if let Some(axes) = metric.separable() {
+        separable_safe_region_size(input, axes, threshold)
+    } else {
+        scanned_safe_region_size(input, metric, threshold)
+    }
 }
 
 #[aoc(day6, part2)]
 fn part_two(input: &[Point2]) -> usize {
-    let bbox = bounding_box(input);
+    safe_region_size(input, &Manhattan, 10000)
+}
 
-    (bbox.0..=bbox.2)
-        .map(|x| (bbox.1..=bbox.3).map(move |y| Point2::new(x, y)))
-        .flat_map(|i| i.into_iter())
-        .filter(|p| input.iter().map(|pp| pp.distance(p)).sum::<i32>() < 10000)
-        .count()
+/// A point with any number of coordinates, using the Manhattan distance
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct PointN {
+    pub coords: Vec<i32>,
+}
+
+impl PointN {
+    pub fn new(coords: Vec<i32>) -> Self {
+        PointN { coords }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.coords.len()
+    }
+
+    pub fn distance(&self, other: &Self) -> i64 {
+        self.coords
+            .iter()
+            .zip(&other.coords)
+            .map(|(a, b)| (i64::from(*a) - i64::from(*b)).abs())
+            .sum()
+    }
+}
+
+impl From<&Point2> for PointN {
+    fn from(p: &Point2) -> Self {
+        PointN::new(vec![p.x, p.y])
+    }
+}
+
+impl FromStr for PointN {
+    type Err = ParseIntError;
+
+    fn from_str(input: &str) -> Result<PointN, Self::Err> {
+        let coords: Result<Vec<i32>, ParseIntError> =
+            input.trim().split(',').map(|c| c.trim().parse()).collect();
+        Ok(PointN::new(coords?))
+    }
+}
+
+/// Parses one point per line, all of them having the same dimension
+pub fn parse_points(input: &str) -> Result<Vec<PointN>, Box<dyn Error>> {
+    let points = input
+        .lines()
+        .map(|l| l.parse::<PointN>())
+        .collect::<Result<Vec<PointN>, ParseIntError>>()?;
+    match points
+        .iter()
+        .find(|p| p.dimension() != points[0].dimension())
+    {
+        Some(p) => Err(format!(
+            "Point {:?} does not have {} coordinates",
+            p.coords,
+            points[0].dimension()
+        )
+        .into()),
+        None => Ok(points),
+    }
+}
+
+/// Computes the bounding box of the points as (minimums, maximums)
+pub fn bounding_box_n(input: &[PointN]) -> (Vec<i32>, Vec<i32>) {
+    let first = input[0].coords.clone();
+    input
+        .iter()
+        .fold((first.clone(), first), |(mut lo, mut hi), p| {
+            p.coords.iter().enumerate().for_each(|(i, &c)| {
+                lo[i] = lo[i].min(c);
+                hi[i] = hi[i].max(c);
+            });
+            (lo, hi)
+        })
+}
+
+/// Computes the area (or volume) of the region of every point within the
+/// bounding box, or None if said region is infinite, i.e. owns a cell on
+/// the border of the bounding box (see `voronoi`)
+pub fn areas_n(input: &[PointN]) -> Vec<Option<usize>> {
+    if input.is_empty() {
+        return vec![];
+    }
+    let (lo, hi) = bounding_box_n(input);
+    let sizes: Vec<usize> = lo
+        .iter()
+        .zip(&hi)
+        .map(|(l, h)| (h - l + 1) as usize)
+        .collect();
+    // Cells are laid out with the first coordinate varying the slowest
+    let mut strides = vec![1; sizes.len()];
+    (0..sizes.len().saturating_sub(1))
+        .rev()
+        .for_each(|i| strides[i] = strides[i + 1] * sizes[i + 1]);
+    let size = strides[0] * sizes[0];
+
+    let sources: Vec<usize> = input
+        .iter()
+        .map(|p| {
+            (0..sizes.len())
+                .map(|i| (p.coords[i] - lo[i]) as usize * strides[i])
+                .sum()
+        })
+        .collect();
+    let (owners, _) = flood_fill(size, &sources, |cell, next| {
+        (0..sizes.len()).for_each(|i| {
+            let c = cell / strides[i] % sizes[i];
+            if c > 0 {
+                next.push(cell - strides[i]);
+            }
+            if c + 1 < sizes[i] {
+                next.push(cell + strides[i]);
+            }
+        });
+    });
+
+    let mut areas = vec![0; input.len()];
+    let mut infinite = vec![false; input.len()];
+    owners.into_iter().enumerate().for_each(|(cell, owner)| {
+        if let Some(owner) = owner {
+            areas[owner] += 1;
+            infinite[owner] |= (0..sizes.len()).any(|i| {
+                let c = cell / strides[i] % sizes[i];
+                c == 0 || c + 1 == sizes[i]
+            });
+        }
+    });
+    areas
+        .into_iter()
+        .zip(infinite)
+        .map(|(area, infinite)| if infinite { None } else { Some(area) })
+        .collect()
+}
+
+/// Counts the cells whose total distance to all the points is below the threshold
+pub fn safe_region_size_n(input: &[PointN], threshold: i64) -> usize {
+    if input.is_empty() {
+        return 0;
+    }
+    let axes: Vec<AxisSums> = (0..input[0].dimension())
+        .map(|i| {
+            let coords: Vec<i64> = input.iter().map(|p| i64::from(p.coords[i])).collect();
+            AxisSums::new(coords, Axis::Linear(1))
+        })
+        .collect();
+    separable_count(&axes, threshold)
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
+    /// Pseudo-random generator for the tests : a linear congruential
+    /// generator, giving numbers below the requested bound
+    fn random(mut seed: u32) -> impl FnMut(u32) -> u32 {
+        move |bound| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % bound
+        }
+    }
+
     #[test]
     fn day6_parse() {
         assert_eq!(
@@ -132,4 +1284,312 @@ pub mod tests {
         let points = generator(input);
         assert_eq!(part_one(&points), 17);
     }
+
+    #[test]
+    fn day6_safe_region() {
+        let points = generator("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+        assert_eq!(safe_region_size(&points, &Manhattan, 32), 16);
+        assert_eq!(safe_region_size(&points, &Manhattan, 0), 0);
+
+        // With a big threshold, the region spreads beyond the bounding box
+        let brute_force = |metric: &dyn Metric, threshold: i64| {
+            (-100..=100)
+                .flat_map(|x| (-100..=100).map(move |y| Point2::new(x, y)))
+                .filter(|c| {
+                    points
+                        .iter()
+                        .map(|p| p.distance_with(c, metric))
+                        .sum::<i64>()
+                        < threshold
+                })
+                .count()
+        };
+        assert_eq!(
+            safe_region_size(&points, &Manhattan, 200),
+            brute_force(&Manhattan, 200)
+        );
+        assert!(brute_force(&Manhattan, 200) > 100);
+
+        // Every metric, separable or not
+        let weighted = Weighted::new(Manhattan, 2, 1).expect("Invalid weights");
+        let metrics: Vec<(&dyn Metric, i64)> = vec![
+            (&Chebyshev, 150),
+            (&SquaredEuclidean, 3000),
+            (&weighted, 300),
+        ];
+        metrics.into_iter().for_each(|(metric, threshold)| {
+            assert_eq!(
+                safe_region_size(&points, metric, threshold),
+                brute_force(metric, threshold)
+            );
+        });
+    }
+
+    #[test]
+    fn day6_axis_sums() {
+        let coords = vec![7, -3, 2, 2, 11];
+        let axes = vec![Axis::Linear(3), Axis::Quadratic(2)];
+        axes.into_iter().for_each(|axis| {
+            let sums = AxisSums::new(coords.clone(), axis);
+            let naive = |x: i64| -> i64 {
+                coords
+                    .iter()
+                    .map(|c| match axis {
+                        Axis::Linear(w) => w * (x - c).abs(),
+                        Axis::Quadratic(w) => w * (x - c) * (x - c),
+                    })
+                    .sum()
+            };
+            (-20..30).for_each(|x| assert_eq!(sums.at(x), naive(x)));
+            let min = (-20..30).map(naive).min();
+            assert_eq!(Some(sums.at(sums.argmin())), min);
+            let mut below: Vec<i64> = (-100..100).map(naive).filter(|&s| s < 400).collect();
+            let mut found = sums.below(400);
+            below.sort();
+            found.sort();
+            assert_eq!(found, below);
+        });
+    }
+
+    #[test]
+    fn day6_areas() {
+        let points = generator("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+        assert_eq!(
+            areas(&points, &Manhattan),
+            vec![None, None, None, Some(9), Some(17), None]
+        );
+
+        // A point inside the bounding box can own an infinite region,
+        // while a surrounded point keeps a finite one
+        let points = generator("0, 0\n10, 0\n5, 1\n0, 10\n10, 10");
+        assert_eq!(areas(&points, &Manhattan)[2], None);
+        let points = generator("0, 5\n5, 0\n5, 10\n10, 5\n5, 5");
+        assert_eq!(areas(&points, &Manhattan)[4], Some(25));
+    }
+
+    #[test]
+    fn day6_voronoi() {
+        let points = generator("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+        let regions = voronoi(&points, &Manhattan);
+        let e = &regions.regions[4];
+        assert_eq!(e.point, Point2::new(5, 5));
+        assert_eq!(e.area(), Some(17));
+        assert!(!e.infinite);
+        assert_eq!(e.bbox, Some((4, 2, 7, 8)));
+        assert!(e.cells.contains(&Point2::new(5, 2)));
+
+        let a = &regions.regions[0];
+        assert!(a.infinite);
+        assert_eq!(a.area(), None);
+        assert_eq!(a.bbox.map(|b| (b.0, b.1)), Some((1, 1)));
+
+        // Cells of the subject shown as '.'
+        assert!(regions.ties.contains(&Point2::new(1, 4)));
+        assert!(regions.ties.contains(&Point2::new(5, 1)));
+        let owned: usize = regions.regions.iter().map(|r| r.cells.len()).sum();
+        assert_eq!(owned + regions.ties.len(), 8 * 9);
+
+        // Duplicated points own nothing
+        let points = generator("1, 1\n1, 1\n3, 3");
+        let regions = voronoi(&points, &Manhattan);
+        assert_eq!(regions.regions[0].bbox, None);
+        assert!(regions.ties.contains(&Point2::new(1, 1)));
+    }
+
+    #[test]
+    fn day6_flood_fill() {
+        // Pseudo-random points, with duplicates and aligned points
+        let mut next = random(42);
+        let points: Vec<Point2> = (0..40)
+            .map(|_| Point2::new(next(60) as i32, next(40) as i32))
+            .chain(vec![Point2::new(10, 10), Point2::new(10, 10)])
+            .collect();
+        let bbox = bounding_box(&points);
+        let metrics: Vec<&dyn Metric> = vec![&Manhattan, &Chebyshev];
+        metrics.into_iter().for_each(|metric| {
+            let moves = metric.neighbours().expect("No neighbours");
+            assert_eq!(
+                owners_flood(&points, bbox, moves).0,
+                owners_scan(&points, bbox, metric)
+            );
+        });
+    }
+
+    /// Tests the areas of the LiveVoronoi through edits
+    #[test]
+    fn day6_live_voronoi() {
+        let points = generator("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+        let mut live = LiveVoronoi::new(&points);
+        assert_eq!(live.area(4), Some(17));
+        assert_eq!(live.area(0), None);
+
+        // Inserting next to E only affects its neighbours
+        let (id, changed) = live.insert(Point2::new(6, 6));
+        assert_eq!(id, 6);
+        assert!(changed.contains(&(4, live.area(4))));
+        assert!(!changed.iter().any(|(i, _)| *i == 0));
+        let mut current = points.clone();
+        current.push(Point2::new(6, 6));
+        let expected = |current: &[Point2]| -> Vec<Option<usize>> { areas(current, &Manhattan) };
+        assert_eq!(
+            live.areas().into_iter().map(|(_, a)| a).collect::<Vec<_>>(),
+            expected(&current)
+        );
+
+        // Removing E gives its cells back
+        assert!(live.remove(4).is_some());
+        assert!(live.remove(4).is_none());
+        assert_eq!(live.get(4), None);
+        current.remove(4);
+        assert_eq!(
+            live.areas().into_iter().map(|(_, a)| a).collect::<Vec<_>>(),
+            expected(&current)
+        );
+
+        // Pseudo-random edits, inside and outside of the frame
+        let mut next = random(7);
+        let mut ids: Vec<usize> = live.areas().into_iter().map(|(i, _)| i).collect();
+        for _ in 0..60 {
+            if ids.len() > 3 && next(3) == 0 {
+                let id = ids.remove(next(ids.len() as u32) as usize);
+                live.remove(id).expect("Failed to remove");
+            } else {
+                let point = Point2::new(next(14) as i32 - 2, next(14) as i32 - 2);
+                ids.push(live.insert(point).0);
+            }
+            let current: Vec<Point2> = ids
+                .iter()
+                .map(|&i| live.get(i).expect("Missing point").clone())
+                .collect();
+            assert_eq!(
+                ids.iter().map(|&i| live.area(i)).collect::<Vec<_>>(),
+                expected(&current)
+            );
+        }
+    }
+
+    #[test]
+    fn day6_dimensions() {
+        let input = "1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9";
+        let points = parse_points(input).expect("Failed to parse");
+        assert_eq!(points[1], PointN::new(vec![1, 6]));
+        assert_eq!(areas_n(&points), areas(&generator(input), &Manhattan));
+        assert_eq!(safe_region_size_n(&points, 32), 16);
+        assert_eq!(safe_region_size_n(&points, 200), {
+            let points2 = generator(input);
+            safe_region_size(&points2, &Manhattan, 200)
+        });
+
+        assert!(parse_points("1, 2, 3\n4, 5").is_err());
+        assert!(parse_points("1, 2, a").is_err());
+
+        // A point surrounded on every side
+        let points = parse_points("2,2,-2\n2,2,6\n2,-2,2\n2,6,2\n-2,2,2\n6,2,2\n2,2,2")
+            .expect("Failed to parse");
+        assert_eq!(bounding_box_n(&points), (vec![-2, -2, -2], vec![6, 6, 6]));
+        let areas = areas_n(&points);
+        assert_eq!(areas[..6], [None; 6]);
+        assert!(areas[6].is_some());
+        let brute_force = (-20..=24)
+            .flat_map(|x| (-20..=24).flat_map(move |y| (-20..=24).map(move |z| (x, y, z))))
+            .filter(|&(x, y, z)| {
+                let cell = PointN::new(vec![x, y, z]);
+                points.iter().map(|p| p.distance(&cell)).sum::<i64>() < 40
+            })
+            .count();
+        assert_eq!(safe_region_size_n(&points, 40), brute_force);
+
+        // The closed center region, checked cell by cell
+        let center = (-2..=6)
+            .flat_map(|x| (-2..=6).flat_map(move |y| (-2..=6).map(move |z| vec![x, y, z])))
+            .filter(|c| {
+                let cell = PointN::new(c.clone());
+                let d: Vec<i64> = points.iter().map(|p| p.distance(&cell)).collect();
+                (0..6).all(|i| d[6] < d[i])
+            })
+            .count();
+        assert_eq!(areas[6], Some(center));
+    }
+
+    #[test]
+    fn day6_metrics() {
+        let (a, b) = (Point2::new(1, 2), Point2::new(4, -2));
+        assert_eq!(a.distance_with(&b, &Manhattan), 7);
+        assert_eq!(a.distance_with(&b, &Chebyshev), 4);
+        assert_eq!(a.distance_with(&b, &SquaredEuclidean), 25);
+        let weighted = Weighted::new(Chebyshev, 1, 2).expect("Invalid weights");
+        assert_eq!(a.distance_with(&b, &weighted), 8);
+        assert!(Weighted::new(Manhattan, 0, 1).is_err());
+        assert!(Weighted::new(SquaredEuclidean, 2, -1).is_err());
+
+        // The regions of the example change shape with the metric
+        let points = generator("1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9");
+        let metrics: Vec<&dyn Metric> = vec![&Manhattan, &Chebyshev, &SquaredEuclidean];
+        metrics.into_iter().for_each(|metric| {
+            assert_eq!(areas(&points, metric), padded_areas(&points, metric, 60));
+        });
+        assert_eq!(areas(&points, &Manhattan)[3..5], [Some(9), Some(17)]);
+    }
+
+    /// Areas found by scanning far beyond the points : regions owning a
+    /// cell on the border of the padded box are deemed infinite
+    fn padded_areas(points: &[Point2], metric: &dyn Metric, pad: i32) -> Vec<Option<usize>> {
+        let b = bounding_box(points);
+        let (x0, y0, x1, y1) = (b.0 - pad, b.1 - pad, b.2 + pad, b.3 + pad);
+        let mut areas = vec![Some(0); points.len()];
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                if let Some(i) = closest(points, &Point2::new(x, y), metric) {
+                    if x == x0 || x == x1 || y == y0 || y == y1 {
+                        areas[i] = None;
+                    } else if let Some(area) = areas[i].as_mut() {
+                        *area += 1;
+                    }
+                }
+            }
+        }
+        areas
+    }
+
+    #[test]
+    fn day6_infinite_regions() {
+        // The region of (5, 7) reaches the bounding box, yet is finite
+        let points = generator("10, 9\n5, 0\n4, 8\n9, 3\n7, 2\n5, 7");
+        assert_eq!(areas(&points, &Chebyshev)[5], Some(16));
+
+        // Points on an edge of the convex hull have infinite regions
+        let points = generator("0, 0\n4, 0\n8, 0\n4, 4");
+        let regions = voronoi(&points, &SquaredEuclidean);
+        assert!(regions.regions[1].infinite);
+        let points = generator("0, 0\n10, 0\n5, 10\n5, 1");
+        assert!(!voronoi(&points, &SquaredEuclidean).regions[3].infinite);
+        // ... while finite ones may leave the bounding box
+        let e = &voronoi(&points, &SquaredEuclidean).regions[3];
+        assert!(e.cells.contains(&Point2::new(5, -1)));
+
+        // Regions of points close to the hull reach far : pad generously
+        let mut next = random(11);
+        let metrics: Vec<Box<dyn Metric>> = vec![
+            Box::new(Manhattan),
+            Box::new(Chebyshev),
+            Box::new(SquaredEuclidean),
+            Box::new(Weighted::new(Manhattan, 2, 1).expect("Invalid weights")),
+            Box::new(Weighted::new(Chebyshev, 1, 2).expect("Invalid weights")),
+            Box::new(Weighted::new(SquaredEuclidean, 2, 1).expect("Invalid weights")),
+        ];
+        for _ in 0..12 {
+            let points: Vec<Point2> = (0..6)
+                .map(|_| Point2::new(next(12) as i32, next(12) as i32))
+                .collect();
+            metrics.iter().for_each(|metric| {
+                assert_eq!(
+                    areas(&points, metric.as_ref()),
+                    padded_areas(&points, metric.as_ref(), 400),
+                    "{:?} with {:?}",
+                    points,
+                    metric.geometry()
+                );
+            });
+        }
+    }
 }
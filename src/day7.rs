@@ -1,52 +1,151 @@
+use fnv::FnvHashMap;
+use serde_json::json;
 use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Node {
-    pub name: u8,
-    depends: Vec<u8>,
+    pub name: String,
+    depends: Vec<String>,
 }
 
 impl Node {
-    pub fn add_dependency(&mut self, dep: u8) {
-        self.depends.push(dep);
+    pub fn add_dependency(&mut self, dep: &str) {
+        self.depends.push(dep.into());
     }
 
-    pub fn new(name: u8, first_dep: u8) -> Self {
-        let depends = vec![first_dep];
-        Node { name, depends }
+    pub fn new(name: &str, first_dep: &str) -> Self {
+        let depends = vec![first_dep.into()];
+        Node {
+            name: name.into(),
+            depends,
+        }
     }
 
-    pub fn new_empty(name: u8) -> Self {
+    pub fn new_empty(name: &str) -> Self {
         Node {
-            name,
+            name: name.into(),
             depends: vec![],
         }
     }
 
-    pub fn deps_ok(&self, solved: &[u8]) -> bool {
-        self.depends.len() == 0 || self.depends.iter().all(|d| solved.iter().any(|i| d == i))
+    pub fn deps_ok(&self, solved: &[String]) -> bool {
+        self.depends.is_empty() || self.depends.iter().all(|d| solved.iter().any(|i| d == i))
     }
+}
+
+/// Time needed by each step : explicit durations, or by default
+/// `additional_time` plus the rank of the step name in the alphabet
+/// (`A` is 1, `Z` is 26). Names that are not a single letter from A to Z
+/// default to `additional_time` plus 1.
+#[derive(Clone, Debug, Default)]
+pub struct Durations {
+    pub additional_time: u32,
+    overrides: FnvHashMap<String, u32>,
+}
 
-    pub fn duration(&self, additional_time: u8) -> u32 {
-        (self.name - 'A' as u8 + additional_time + 1).into()
+impl Durations {
+    pub fn new(additional_time: u32) -> Self {
+        Durations {
+            additional_time,
+            overrides: FnvHashMap::default(),
+        }
+    }
+
+    /// Sets the duration of a step, overriding the default one.
+    /// A step lasts at least 1 second : a null duration is an error.
+    pub fn set(&mut self, name: &str, duration: u32) -> Result<(), Box<dyn Error>> {
+        if duration == 0 {
+            return Err(format!("Step {} lasts at least 1 second", name).into());
+        }
+        self.overrides.insert(name.into(), duration);
+        Ok(())
+    }
+
+    /// Gets the duration of a step
+    pub fn get(&self, name: &str) -> u32 {
+        if let Some(d) = self.overrides.get(name) {
+            return *d;
+        }
+        let rank = match name.as_bytes() {
+            [c @ b'A'..=b'Z'] => u32::from(c - b'A') + 1,
+            _ => 1,
+        };
+        self.additional_time + rank
+    }
+}
+
+/// Parses a duration table : one step per line, as "name duration".
+/// Empty lines and lines starting with "//" are ignored.
+/// The additional time is 0, and can be set afterwards.
+impl FromStr for Durations {
+    type Err = Box<dyn Error>;
+
+    fn from_str(input: &str) -> Result<Self, Box<dyn Error>> {
+        let mut durations = Durations::new(0);
+        for (i, l) in input.lines().enumerate() {
+            let line = l.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.rsplitn(2, char::is_whitespace).collect();
+            match parts.as_slice() {
+                [duration, name] => {
+                    let duration: u32 = duration
+                        .parse()
+                        .map_err(|e| format!("Line {}: invalid duration : {}", i + 1, e))?;
+                    durations
+                        .set(name.trim(), duration)
+                        .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+                }
+                _ => return Err(format!("Line {}: expected a name and a duration", i + 1).into()),
+            }
+        }
+        Ok(durations)
+    }
+}
+
+/// The order in which available steps are picked
+pub trait StepOrder {
+    fn compare(&self, a: &str, b: &str) -> Ordering;
+}
+
+/// Alphabetical order of the subject, extended to any name
+pub struct Lexical;
+
+impl StepOrder for Lexical {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        a.cmp(b)
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Dependency {
-    pub name: u8,
-    pub depends_on: u8,
+    pub name: String,
+    pub depends_on: String,
 }
 
 impl FromStr for Dependency {
-    type Err = ();
-    fn from_str(input: &str) -> Result<Self, ()> {
-        let parts: Vec<&str> = input.trim().split(" ").collect();
-        Ok(Dependency {
-            name: parts[7].as_bytes()[0],
-            depends_on: parts[1].as_bytes()[0],
-        })
+    type Err = Box<dyn Error>;
+
+    fn from_str(input: &str) -> Result<Self, Box<dyn Error>> {
+        let line = input.trim();
+        let parts: Option<(&str, &str)> = line
+            .strip_prefix("Step ")
+            .and_then(|l| l.strip_suffix(" can begin."))
+            .and_then(|l| l.split_once(" must be finished before step "));
+        match parts {
+            Some((depends_on, name)) if !depends_on.is_empty() && !name.is_empty() => {
+                Ok(Dependency {
+                    name: name.into(),
+                    depends_on: depends_on.into(),
+                })
+            }
+            _ => Err(format!("Invalid dependency \"{}\"", line).into()),
+        }
     }
 }
 
@@ -62,50 +161,187 @@ impl PartialOrd for Node {
     }
 }
 
+/// Problem that prevents completing the steps of a dependency graph
+#[derive(Debug, PartialEq, Eq)]
+pub enum GraphError {
+    /// Steps that must be finished before one another, in this order,
+    /// the first one being repeated at the end
+    Cycle(Vec<String>),
+    /// A step depending on a step that does not exist.
+    /// Only nodes built by hand can raise it : `gen_nodes` creates a node
+    /// for every step named in the instructions, prerequisites included.
+    UnknownStep { step: String, dependency: String },
+    /// No worker to run the steps
+    NoWorker,
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::Cycle(steps) => write!(f, "Cycle : {}", steps.join(" → ")),
+            GraphError::UnknownStep { step, dependency } => {
+                write!(f, "Step {} depends on unknown step {}", step, dependency)
+            }
+            GraphError::NoWorker => write!(f, "No worker to run the steps"),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+/// Finds the cycles through the given node, using a depth-first search
+/// over the dependencies. `path` holds the nodes being explored.
+fn find_cycles<'a>(
+    node: &'a Node,
+    nodes: &FnvHashMap<&'a str, &'a Node>,
+    path: &mut Vec<&'a str>,
+    done: &mut FnvHashMap<&'a str, bool>,
+    errors: &mut Vec<GraphError>,
+) {
+    path.push(&node.name);
+    done.insert(&node.name, false);
+    for dep in &node.depends {
+        let dep: &str = dep;
+        match (done.get(dep), nodes.get(dep)) {
+            (Some(false), _) => {
+                // Back to a node being explored : the path from it is a cycle
+                let start = path.iter().position(|n| *n == dep).unwrap_or(0);
+                let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(dep.into());
+                cycle.reverse();
+                errors.push(GraphError::Cycle(cycle));
+            }
+            (None, Some(next)) => find_cycles(next, nodes, path, done, errors),
+            _ => (),
+        }
+    }
+    done.insert(&node.name, true);
+    path.pop();
+}
+
+/// Checks that every step can be completed : reports the dependencies
+/// on unknown steps, and the cycles of steps waiting for one another
+pub fn validate(input: &[Node]) -> Vec<GraphError> {
+    let nodes: FnvHashMap<&str, &Node> = input.iter().map(|n| (n.name.as_str(), n)).collect();
+    let mut errors: Vec<GraphError> = input
+        .iter()
+        .flat_map(|n| {
+            n.depends
+                .iter()
+                .filter(|d| !nodes.contains_key(d.as_str()))
+                .map(move |d| GraphError::UnknownStep {
+                    step: n.name.clone(),
+                    dependency: d.clone(),
+                })
+        })
+        .collect();
+
+    let mut sorted: Vec<&Node> = input.iter().collect();
+    sorted.sort();
+    let mut done: FnvHashMap<&str, bool> = FnvHashMap::default();
+    for node in sorted {
+        if !done.contains_key(node.name.as_str()) {
+            find_cycles(node, &nodes, &mut vec![], &mut done, &mut errors);
+        }
+    }
+    errors
+}
+
+/// Validates the graph, keeping the first problem found
+fn check(input: &[Node]) -> Result<(), GraphError> {
+    match validate(input).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// A step run by a worker, from the second `start` to the second `end` excluded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Task {
+    /// Index of the worker, from 0
+    pub worker: usize,
+    pub step: String,
+    pub start: u32,
+    pub end: u32,
+}
+
 pub struct WorkerManager {
     nodes: Vec<Node>,
-    workers: Vec<Option<(u8, u32)>>,
-    additional_time: u8,
+    /// Step, remaining time and start of the current task of every worker
+    workers: Vec<Option<(String, u32, u32)>>,
+    durations: Durations,
     duration: u32,
+    /// Completed tasks, in the order of completion
+    tasks: Vec<Task>,
 }
 
 impl WorkerManager {
-    pub fn new(nodes: Vec<Node>, nb_workers: u32, additional_time: u8) -> Self {
-        let workers: Vec<Option<(u8, u32)>> = (1..=nb_workers).map(|_| None).collect();
+    /// Creates a `WorkerManager`, that picks the available steps
+    /// following the given order
+    pub fn new(
+        mut nodes: Vec<Node>,
+        nb_workers: u32,
+        durations: Durations,
+        order: &dyn StepOrder,
+    ) -> Self {
+        nodes.sort_by(|a, b| order.compare(&a.name, &b.name));
+        let workers: Vec<Option<(String, u32, u32)>> = (1..=nb_workers).map(|_| None).collect();
         WorkerManager {
             nodes,
             workers,
-            additional_time,
+            durations,
             duration: 0,
+            tasks: vec![],
         }
     }
 
-    pub fn solve(&mut self) {
-        let mut solved: Vec<u8> = vec![];
+    /// Time needed to complete every step
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    /// Completed tasks, in the order of completion
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    /// Runs the workers until every step is completed.
+    /// Fails without running them if some steps can't be completed.
+    pub fn solve(&mut self) -> Result<(), GraphError> {
+        if self.workers.is_empty() {
+            return Err(GraphError::NoWorker);
+        }
+        check(&self.nodes)?;
+        let mut solved: Vec<String> = vec![];
         let target = self.nodes.len();
         while solved.len() != target {
             for w in self.workers.iter_mut().filter(|w| w.is_none()) {
-                if let Some(node) = self.nodes.iter().find(|n| n.deps_ok(&solved)) {
-                    *w = Some((node.name, node.duration(self.additional_time)));
-                    let idx_first = self
-                        .nodes
-                        .iter()
-                        .position(|n| n.name == node.name)
-                        .expect("Failed to find node ...");
-                    self.nodes.remove(idx_first);
+                if let Some(idx_first) = self.nodes.iter().position(|n| n.deps_ok(&solved)) {
+                    let node = self.nodes.remove(idx_first);
+                    let duration = self.durations.get(&node.name);
+                    *w = Some((node.name, duration, self.duration));
                 }
             }
 
-            for w in self.workers.iter_mut().filter(|w| w.is_some()) {
-                *w = Some((w.unwrap().0, w.unwrap().1 - 1));
-                if w.unwrap().1 == 0 {
-                    solved.push(w.unwrap().0);
-                    *w = None;
+            for (i, w) in self.workers.iter_mut().enumerate() {
+                if let Some((name, remaining, start)) = w {
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        solved.push(name.clone());
+                        self.tasks.push(Task {
+                            worker: i,
+                            step: name.clone(),
+                            start: *start,
+                            end: self.duration + 1,
+                        });
+                        *w = None;
+                    }
                 }
             }
 
             self.duration += 1;
         }
+        Ok(())
     }
 }
 
@@ -116,13 +352,13 @@ pub fn gen_nodes(input: &str) -> Vec<Node> {
     input
         .lines()
         .map(|l| l.parse::<Dependency>().expect("Failed to parse Dependency"))
-        .for_each(|d| match nodes.iter_mut().find(|n| n.name == d.name) {
-            Some(n) => n.add_dependency(d.depends_on),
-            _ => {
-                nodes.push(Node::new(d.name, d.depends_on));
-                if nodes.iter().filter(|n| n.name == d.depends_on).count() == 0 {
-                    nodes.push(Node::new_empty(d.depends_on));
-                }
+        .for_each(|d| {
+            match nodes.iter_mut().find(|n| n.name == d.name) {
+                Some(n) => n.add_dependency(&d.depends_on),
+                _ => nodes.push(Node::new(&d.name, &d.depends_on)),
+            }
+            if !nodes.iter().any(|n| n.name == d.depends_on) {
+                nodes.push(Node::new_empty(&d.depends_on));
             }
         });
 
@@ -130,34 +366,122 @@ pub fn gen_nodes(input: &str) -> Vec<Node> {
     nodes
 }
 
-#[aoc(day7, part1)]
-pub fn part_one(input: &[Node]) -> String {
+/// Computes the order in which the steps are completed by a single worker,
+/// picking the available steps following the given order
+pub fn step_order(input: &[Node], order: &dyn StepOrder) -> Result<Vec<String>, GraphError> {
+    check(input)?;
     let mut nodes: Vec<Node> = input.to_vec();
-    let mut solved: Vec<u8> = vec![];
-    let mut order: String = "".into();
-    while solved.len() < input.len() {
-        let first = nodes
-            .iter()
-            .position(|n| n.deps_ok(&solved))
-            .expect("Failed to find node ...");
-
-        let node = &nodes[first];
-        order.push(node.name as char);
-        solved.push(node.name);
-        nodes.remove(first);
+    nodes.sort_by(|a, b| order.compare(&a.name, &b.name));
+    let mut solved: Vec<String> = vec![];
+    while let Some(first) = nodes.iter().position(|n| n.deps_ok(&solved)) {
+        solved.push(nodes.remove(first).name);
     }
-    order
+    Ok(solved)
+}
+
+#[aoc(day7, part1)]
+pub fn part_one(input: &[Node]) -> Result<String, GraphError> {
+    Ok(step_order(input, &Lexical)?.concat())
 }
 
 #[aoc(day7, part2)]
-pub fn part_two(input: &[Node]) -> u32 {
+pub fn part_two(input: &[Node]) -> Result<u32, GraphError> {
     calc_duration(input, 5, 60)
 }
 
-fn calc_duration(input: &[Node], workers: u32, additional_time: u8) -> u32 {
-    let mut worker_manager = WorkerManager::new(input.to_vec(), workers, additional_time);
-    worker_manager.solve();
-    worker_manager.duration
+fn calc_duration(input: &[Node], workers: u32, additional_time: u32) -> Result<u32, GraphError> {
+    duration_with(input, workers, Durations::new(additional_time), &Lexical)
+}
+
+/// Computes the time needed by the workers to complete every step
+pub fn duration_with(
+    input: &[Node],
+    workers: u32,
+    durations: Durations,
+    order: &dyn StepOrder,
+) -> Result<u32, GraphError> {
+    Ok(schedule(input, workers, durations, order)?.duration())
+}
+
+/// Runs the workers over every step, keeping track of who does what and when
+pub fn schedule(
+    input: &[Node],
+    workers: u32,
+    durations: Durations,
+    order: &dyn StepOrder,
+) -> Result<WorkerManager, GraphError> {
+    let mut worker_manager = WorkerManager::new(input.to_vec(), workers, durations, order);
+    worker_manager.solve()?;
+    Ok(worker_manager)
+}
+
+/// Renders the second-by-second table of the subject :
+/// ```text
+/// Second   Worker 1   Worker 2   Done
+///    0        C          .
+///    1        C          .
+///    2        C          .
+///    3        A          F       C
+/// ```
+pub fn render_schedule(manager: &WorkerManager) -> String {
+    let tasks = manager.tasks();
+    let nb_workers = manager.workers.len();
+    // Worker columns are wide enough for the longest step name
+    let width = tasks
+        .iter()
+        .map(|t| t.step.chars().count())
+        .chain((1..=nb_workers).map(|w| format!("Worker {}", w).len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut table = String::from("Second");
+    (1..=nb_workers).for_each(|w| {
+        table.push_str(&format!(
+            "   {:^width$}",
+            format!("Worker {}", w),
+            width = width
+        ))
+    });
+    table.push_str("   Done");
+    for second in 0..=manager.duration() {
+        let mut row = format!("{:>4}  ", second);
+        (0..nb_workers).for_each(|w| {
+            let step = tasks
+                .iter()
+                .find(|t| t.worker == w && t.start <= second && second < t.end)
+                .map_or(".", |t| t.step.as_str());
+            row.push_str(&format!("   {:^width$}", step, width = width));
+        });
+        let done: String = tasks
+            .iter()
+            .filter(|t| t.end <= second)
+            .map(|t| t.step.as_str())
+            .collect();
+        row.push_str(&format!("   {}", done));
+        table.push('\n');
+        table.push_str(row.trim_end());
+    }
+    table
+}
+
+/// Exports the tasks as a Gantt-style JSON list, ordered by start then
+/// worker, with workers numbered from 1 as in the table :
+/// `[{"worker": 1, "step": "C", "start": 0, "end": 3}, ...]`
+pub fn schedule_json(manager: &WorkerManager) -> String {
+    let mut tasks: Vec<&Task> = manager.tasks().iter().collect();
+    tasks.sort_by_key(|t| (t.start, t.worker));
+    let rows: Vec<serde_json::Value> = tasks
+        .into_iter()
+        .map(|t| {
+            json!({
+                "worker": t.worker + 1,
+                "step": t.step,
+                "start": t.start,
+                "end": t.end,
+            })
+        })
+        .collect();
+    serde_json::Value::from(rows).to_string()
 }
 
 #[cfg(test)]
@@ -179,22 +503,146 @@ Step F must be finished before step E can begin.";
                 .parse::<Dependency>()
                 .expect("Failed to parse dependency"),
             Dependency {
-                name: 'A' as u8,
-                depends_on: 'C' as u8
+                name: "A".into(),
+                depends_on: "C".into()
+            }
+        );
+        assert_eq!(
+            "Step Build docs must be finished before step Release 1.0 can begin."
+                .parse::<Dependency>()
+                .expect("Failed to parse dependency"),
+            Dependency {
+                name: "Release 1.0".into(),
+                depends_on: "Build docs".into()
             }
-        )
+        );
+        assert!("Step C must be finished".parse::<Dependency>().is_err());
     }
 
     #[test]
     fn day7_part_one() {
         let nodes = gen_nodes(INPUT_TEST);
-        assert_eq!(part_one(&nodes), "CABDFE");
+        assert_eq!(part_one(&nodes), Ok("CABDFE".into()));
     }
 
     #[test]
     fn day7_part_two() {
         let nodes = gen_nodes(INPUT_TEST);
-        assert_eq!(calc_duration(&nodes, 2, 0), 15)
+        assert_eq!(calc_duration(&nodes, 2, 0), Ok(15))
     }
 
+    #[test]
+    fn day7_names_and_durations() {
+        let input = "Step fetch must be finished before step build can begin.
+Step build must be finished before step test can begin.
+Step build must be finished before step lint can begin.
+Step lint must be finished before step ship can begin.
+Step test must be finished before step ship can begin.";
+        let nodes = gen_nodes(input);
+        assert_eq!(
+            step_order(&nodes, &Lexical).expect("Invalid graph"),
+            vec!["fetch", "build", "lint", "test", "ship"]
+        );
+
+        // Picks the last names first
+        struct Reversed;
+        impl StepOrder for Reversed {
+            fn compare(&self, a: &str, b: &str) -> Ordering {
+                b.cmp(a)
+            }
+        }
+        assert_eq!(
+            step_order(&nodes, &Reversed).expect("Invalid graph"),
+            vec!["fetch", "build", "test", "lint", "ship"]
+        );
+
+        let mut durations: Durations = "fetch 3\nbuild 10\n// slow\ntest 20"
+            .parse()
+            .expect("Failed to parse durations");
+        durations.additional_time = 4;
+        assert_eq!(durations.get("test"), 20);
+        assert_eq!(durations.get("lint"), 5);
+        assert_eq!(durations.get("C"), 7);
+        assert!(durations.set("lint", 0).is_err());
+        assert_eq!(durations.get("lint"), 5);
+        assert_eq!(
+            duration_with(&nodes, 2, durations, &Lexical),
+            Ok(3 + 10 + 20 + 5)
+        );
+        assert!("fetch three".parse::<Durations>().is_err());
+        assert!("fetch 0".parse::<Durations>().is_err());
+        assert!("fetch".parse::<Durations>().is_err());
+    }
+
+    #[test]
+    fn day7_validate() {
+        assert_eq!(validate(&gen_nodes(INPUT_TEST)), vec![]);
+
+        // C first appears as a prerequisite of a known step
+        let nodes = gen_nodes(
+            "Step A must be finished before step B can begin.
+Step C must be finished before step B can begin.",
+        );
+        assert_eq!(validate(&nodes), vec![]);
+        assert_eq!(part_one(&nodes), Ok("ACB".into()));
+
+        let cycle = "Step A must be finished before step C can begin.
+Step C must be finished before step F can begin.
+Step F must be finished before step A can begin.
+Step F must be finished before step B can begin.";
+        let nodes = gen_nodes(cycle);
+        let expected = GraphError::Cycle(vec!["A".into(), "C".into(), "F".into(), "A".into()]);
+        assert_eq!(validate(&nodes), vec![expected]);
+        assert_eq!(validate(&nodes)[0].to_string(), "Cycle : A → C → F → A");
+        assert!(part_one(&nodes).is_err());
+        assert!(part_two(&nodes).is_err());
+
+        let mut nodes = gen_nodes(INPUT_TEST);
+        nodes[0].add_dependency("G");
+        assert_eq!(
+            validate(&nodes),
+            vec![GraphError::UnknownStep {
+                step: "A".into(),
+                dependency: "G".into()
+            }]
+        );
+        assert!(part_one(&nodes).is_err());
+        assert!(part_two(&nodes).is_err());
+    }
+
+    #[test]
+    fn day7_schedule() {
+        let nodes = gen_nodes(INPUT_TEST);
+        let manager = schedule(&nodes, 2, Durations::new(0), &Lexical).expect("Invalid graph");
+        assert_eq!(manager.duration(), 15);
+        assert_eq!(
+            duration_with(&nodes, 0, Durations::new(0), &Lexical),
+            Err(GraphError::NoWorker)
+        );
+        assert_eq!(
+            manager.tasks()[1],
+            Task {
+                worker: 0,
+                step: "A".into(),
+                start: 3,
+                end: 4
+            }
+        );
+
+        let table = render_schedule(&manager);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 17);
+        assert_eq!(lines[0], "Second   Worker 1   Worker 2   Done");
+        assert_eq!(lines[1], "   0        C          .");
+        assert_eq!(lines[4], "   3        A          F       C");
+        assert_eq!(lines[16], "  15        .          .       CABFDE");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&schedule_json(&manager)).expect("Invalid JSON");
+        assert_eq!(json.as_array().map(|a| a.len()), Some(6));
+        assert_eq!(
+            json[2],
+            json!({"worker": 2, "step": "F", "start": 3, "end": 9})
+        );
+    }
 }
@@ -16,12 +16,12 @@ mod day13;
 mod day14;
 mod day16;
 mod day17;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
 mod day8;
 mod day9;
 